@@ -1,5 +1,9 @@
 use clap::{CommandFactory, Parser};
-use miette::{IntoDiagnostic, Result};
+use code_gen::{CodeGenError, ExtensionConfig, Extensions, GenOptions, ModuleOptions};
+use miette::{
+    Diagnostic, IntoDiagnostic, LabeledSpan, NamedSource, Report, Result, SourceCode, SourceSpan,
+};
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
@@ -9,18 +13,143 @@ use std::path::{Path, PathBuf};
 struct Cli {
     #[arg(short, long, value_name = "FILE")]
     file: Option<PathBuf>,
+
+    /// Use `z.unknown()` instead of `z.any()` for arrays without items
+    #[arg(long)]
+    unknown_array_items: bool,
+
+    /// JSON file with templates for custom joi types and rules, like
+    /// `{ "types": { "money": "z.number()" }, "rules": { "slug": "regex(/^[a-z-]+$/)" } }`
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Put a `// TODO` comment before each schema with parts that can't be converted
+    #[arg(long)]
+    todo_comments: bool,
+
+    /// Fail instead of leaving out parts of the joi schema that can't be converted
+    #[arg(long)]
+    strict: bool,
+
+    /// Generate a module importing zod and exporting the schema, or each schema of a list of
+    /// them, as `<Name>Schema` named by the `className` meta
+    #[arg(long)]
+    module: bool,
+
+    /// Where the module imports `z` from
+    #[arg(
+        long,
+        value_name = "SPECIFIER",
+        default_value = "zod",
+        requires = "module"
+    )]
+    zod_import: String,
+
+    /// Generate a JavaScript module instead of TypeScript
+    #[arg(long, requires = "module")]
+    js: bool,
+
+    /// Export the inferred type of each schema from the module
+    #[arg(long, requires = "module", conflicts_with = "js")]
+    export_types: bool,
+}
+
+fn read_extensions(config_path: Option<&Path>) -> Result<Extensions> {
+    let mut extensions = Extensions::default();
+    if let Some(config_path) = config_path {
+        let contents = std::fs::read_to_string(config_path).into_diagnostic()?;
+        let config: ExtensionConfig = serde_json::from_str(&contents).into_diagnostic()?;
+        extensions.register_config(config);
+    }
+    Ok(extensions)
+}
+
+/// The byte offset of a line and column from serde, both starting at 1
+fn line_column_offset(contents: &str, line: usize, column: usize) -> Option<usize> {
+    let line_start: usize = contents
+        .split_inclusive('\n')
+        .take(line.checked_sub(1)?)
+        .map(str::len)
+        .sum();
+    Some(line_start + column.saturating_sub(1))
+}
+
+/// A conversion error, pointing into the input file when the error has a place in it
+#[derive(Debug)]
+struct ErrorReport {
+    error: CodeGenError,
+    source_code: NamedSource,
+    label: Option<LabeledSpan>,
+}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
 }
 
-fn run_codegen(file_path: &Path) -> Result<String> {
+impl std::error::Error for ErrorReport {}
+
+impl Diagnostic for ErrorReport {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.error.code()))
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.label
+            .as_ref()
+            .map(|_| &self.source_code as &dyn SourceCode)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let label = self.label.clone()?;
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+fn error_report(file_path: &Path, contents: &str, error: CodeGenError) -> ErrorReport {
+    let label: Option<(SourceSpan, String)> = match &error {
+        CodeGenError::InvalidSchema { path, joi_type, .. } => code_gen::path_span(contents, path)
+            .map(|span| {
+                let label = match joi_type {
+                    Some(joi_type) => format!("expected a joi `{}` schema", joi_type),
+                    None => "expected a joi schema".to_string(),
+                };
+                (span, label)
+            }),
+        CodeGenError::ParseError(parse_error) => {
+            line_column_offset(contents, parse_error.line(), parse_error.column())
+                .map(|offset| (offset.into(), "here".to_string()))
+        }
+        _ => None,
+    };
+    ErrorReport {
+        error,
+        source_code: NamedSource::new(file_path.display().to_string(), contents.to_string()),
+        label: label.map(|(span, label)| LabeledSpan::new_with_span(Some(label), span)),
+    }
+}
+
+fn run_codegen(file_path: &Path, options: &GenOptions) -> Result<String> {
     let mut file = File::open(file_path).into_diagnostic()?;
     let mut contents = String::new();
     file.read_to_string(&mut contents).into_diagnostic()?;
-    code_gen::gen(contents).into_diagnostic()
+    let output = code_gen::gen_with_diagnostics(contents.clone(), options)
+        .map_err(|error| Report::new(error_report(file_path, &contents, error)))?;
+    for diagnostic in output.diagnostics {
+        eprintln!("{:?}", miette::Report::new(diagnostic));
+    }
+    Ok(output.code)
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    // renders the source snippets and labels, the default handler only debug prints them
+    miette::set_hook(Box::new(|_| {
+        Box::new(miette::NarratableReportHandler::new())
+    }))?;
+
     let file_path = cli.file.as_deref().unwrap_or_else(|| {
         Cli::command()
             .error(
@@ -30,6 +159,73 @@ fn main() -> Result<()> {
             .exit();
     });
 
-    run_codegen(file_path)?;
+    let options = GenOptions {
+        unknown_array_items: cli.unknown_array_items,
+        extensions: read_extensions(cli.config.as_deref())?,
+        todo_comments: cli.todo_comments,
+        strict: cli.strict,
+        module: cli.module.then_some(ModuleOptions {
+            zod_import: cli.zod_import,
+            typescript: !cli.js,
+            export_types: cli.export_types,
+        }),
+    };
+
+    let code = run_codegen(file_path, &options)?;
+    println!("{}", code.trim_end());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use miette::NarratableReportHandler;
+
+    use super::error_report;
+
+    fn render(contents: &str) -> String {
+        let error = code_gen::gen(contents.to_string()).unwrap_err();
+        let report = error_report(Path::new("schema.json"), contents, error);
+        let mut rendered = String::new();
+        NarratableReportHandler::new()
+            .render_report(&mut rendered, &report)
+            .unwrap();
+        rendered
+    }
+
+    #[test]
+    fn test_error_report() {
+        assert_eq!(
+            render("{\n  \"type\": \"array\",\n  \"items\": [{ \"type\": \"object\", \"keys\": [] }]\n}"),
+            r#"invalid joi `object` schema at $.items[0]: invalid type: sequence, expected a map
+    Diagnostic severity: error
+Begin snippet for schema.json starting at line 2, column 1
+
+snippet line 2:   "type": "array",
+snippet line 3:   "items": [{ "type": "object", "keys": [] }]
+    label at line 3, columns 13 to 44: expected a joi `object` schema
+snippet line 4: }
+diagnostic code: joi_to_zod::invalid_schema
+"#
+        );
+        assert_eq!(
+            render("{ \"type\": }"),
+            r#"expected value at line 1 column 11
+    Diagnostic severity: error
+Begin snippet for schema.json starting at line 1, column 1
+
+snippet line 1: { "type": }
+    label at line 1, column 11: here
+diagnostic code: joi_to_zod::parse_error
+"#
+        );
+        assert_eq!(
+            render(r#"{ "type": "number", "flags": { "presence": "sometimes" } }"#),
+            r#"unsupported presence "sometimes" at $.flags.presence
+    Diagnostic severity: error
+diagnostic code: joi_to_zod::unsupported_presence
+"#
+        );
+    }
+}
@@ -0,0 +1,709 @@
+use genco::prelude::js;
+use genco::prelude::*;
+use serde_json::Value;
+
+use crate::diagnostics::Conversion;
+use crate::joi::{is_override, JoiDescribe, Tokenizer};
+use crate::joi_types::{
+    AltSchema, JoiAlternatives, JoiDescribeType, JoiObject, JoiWhen, JoiWhenSwitch,
+};
+use crate::rules::RuleTokens;
+use crate::CodeGenError;
+
+// https://joi.dev/api/?v=17.9.1#anywhencondition-options
+
+/// The conditions on a key if they can all be checked from the object the key is in
+pub fn object_whens(schema: &JoiDescribe) -> Option<&[JoiWhen]> {
+    let whens = schema.whens.as_deref().filter(|whens| !whens.is_empty())?;
+    let resolvable = whens.iter().all(|when| match &when.reference {
+        Some(reference) => reference.reference().is_sibling(),
+        None => true,
+    });
+    resolvable.then_some(whens)
+}
+
+/// The literal values a schema accepts, if it only accepts a known set of them
+pub fn literal_values(schema: &JoiDescribe) -> Option<Vec<Value>> {
+    let values: Vec<Value> = match &schema.type_options {
+        JoiDescribeType::Boolean(_) if !schema.flags.only => vec![true.into(), false.into()],
+        _ if !schema.flags.only => return None,
+        JoiDescribeType::Date(_) => return None,
+        _ => schema
+            .allow
+            .iter()
+            .filter(|value| !is_override(value))
+            .cloned()
+            .collect(),
+    };
+    let literals = values
+        .iter()
+        .all(|value| value.is_string() || value.is_number() || value.is_boolean());
+    (literals && !values.is_empty()).then_some(values)
+}
+
+/// The schema a when checks, and if the check is inverted (`not`)
+fn when_test(when: &JoiWhen) -> Option<(&JoiDescribe, bool)> {
+    match (&when.is, &when.not) {
+        (Some(is), _) => Some((is, false)),
+        (None, Some(not)) => Some((not, true)),
+        (None, None) => None,
+    }
+}
+
+/// JS checking if `value` matches the `is` schema of a condition
+fn is_check(
+    is: &JoiDescribe,
+    value: &js::Tokens,
+    options: &Conversion,
+) -> Result<js::Tokens, CodeGenError> {
+    let check = match literal_values(is) {
+        Some(values) => {
+            let values = values.iter().map(|value| value.to_string());
+            let includes = quote! {[$(for value in values join (, )=> $value)].includes($value)};
+            if is.flags.presence.as_deref() == Some("required") {
+                includes
+            } else {
+                quote! {($value === undefined || $includes)}
+            }
+        }
+        None => {
+            let is = is.to_tokens(true, options)?;
+            quote! {$is.safeParse($value).success}
+        }
+    };
+    Ok(check)
+}
+
+/// JS checking if a condition matches
+fn when_check(
+    when: &JoiWhen,
+    value: &js::Tokens,
+    options: &Conversion,
+) -> Result<js::Tokens, CodeGenError> {
+    let check = match when_test(when) {
+        Some((is, false)) => is_check(is, value, options)?,
+        Some((not, true)) => {
+            let check = is_check(not, value, options)?;
+            quote! {!($check)}
+        }
+        // without `is` joi checks the value is truthy
+        None => quote! {![undefined, null, false, 0, ""].includes($value)},
+    };
+    Ok(check)
+}
+
+/// The value a condition checks inside of the object's super refine
+fn when_value(key: &str, when: &JoiWhen) -> js::Tokens {
+    match &when.reference {
+        Some(reference) => {
+            let path = reference.reference().path.iter();
+            quote! {resolve([$(for part in path join (, )=> $[str]($[const](part)))])}
+        }
+        None => quote! {val[$[str]($[const](key))]},
+    }
+}
+
+/// A nested ternary picking the schema of a key based on its conditions
+fn branches(
+    key: &str,
+    base: &JoiDescribe,
+    whens: &[JoiWhen],
+    options: &Conversion,
+) -> Result<js::Tokens, CodeGenError> {
+    let Some((when, rest)) = whens.split_first() else {
+        options.record_branch_whens(base);
+        return base.to_tokens(true, options);
+    };
+    let value = when_value(key, when);
+
+    if let Some(cases) = &when.switch {
+        return switch_branches(key, base, cases, when, rest, options);
+    }
+
+    let after_match = if when.r#break { &[] } else { rest };
+    let then = match &when.then {
+        Some(then) => branches(key, &base.concat(then), after_match, options)?,
+        None => branches(key, base, after_match, options)?,
+    };
+    let otherwise = match &when.otherwise {
+        Some(otherwise) => branches(key, &base.concat(otherwise), rest, options)?,
+        None => branches(key, base, rest, options)?,
+    };
+    let check = when_check(when, &value, options)?;
+    Ok(quote! {($check ? $then : $otherwise)})
+}
+
+/// The first matching case of a switch wins, the last case's otherwise applies if none match
+fn switch_branches(
+    key: &str,
+    base: &JoiDescribe,
+    cases: &[JoiWhenSwitch],
+    when: &JoiWhen,
+    rest: &[JoiWhen],
+    options: &Conversion,
+) -> Result<js::Tokens, CodeGenError> {
+    let Some((case, other_cases)) = cases.split_first() else {
+        return branches(key, base, rest, options);
+    };
+    let value = when_value(key, when);
+    let after_match = if when.r#break { &[] } else { rest };
+
+    let then = match &case.then {
+        Some(then) => branches(key, &base.concat(then), after_match, options)?,
+        None => branches(key, base, after_match, options)?,
+    };
+    let otherwise = match (&case.otherwise, other_cases.is_empty()) {
+        (Some(otherwise), true) => branches(key, &base.concat(otherwise), rest, options)?,
+        _ => switch_branches(key, base, other_cases, when, rest, options)?,
+    };
+    let check = is_check(&case.is, &value, options)?;
+    Ok(quote! {($check ? $then : $otherwise)})
+}
+
+/// The schema for a key with conditions, loose enough to allow every branch. The object's super
+/// refine checks the branch that applies
+fn loose_key(schema: &JoiDescribe, options: &Conversion) -> Result<js::Tokens, CodeGenError> {
+    let mut base = schema.clone();
+    base.whens = None;
+    if base.flags.default.is_none() {
+        base.flags.presence = Some("optional".to_string());
+    }
+    base.to_tokens(true, options)
+}
+
+/// Pick the branch of a key's conditions for a known discriminator value, if it can be known
+fn resolve_branch(schema: &JoiDescribe, whens: &[JoiWhen], value: &Value) -> Option<JoiDescribe> {
+    let mut branch = schema.clone();
+    branch.whens = None;
+    for when in whens {
+        let (is, inverted) = when_test(when)?;
+        let matched = literal_values(is)?.contains(value) != inverted;
+        let apply = if matched { &when.then } else { &when.otherwise };
+        if let Some(apply) = apply {
+            branch = branch.concat(apply);
+        }
+        if matched && when.r#break {
+            break;
+        }
+    }
+    Some(branch)
+}
+
+/// The matches of an alternatives key if some are conditional and can all be checked from the
+/// object the key is in
+pub fn object_alternatives(schema: &JoiDescribe) -> Option<&[AltSchema]> {
+    let JoiDescribeType::Alternatives(alt) = &schema.type_options else {
+        return None;
+    };
+    let mut conditionals = alt.matches.iter().filter_map(|one_match| match one_match {
+        AltSchema::Conditional(when) => Some(when),
+        AltSchema::Schema { .. } => None,
+    });
+    let first = conditionals.next()?;
+    let resolvable = std::iter::once(first)
+        .chain(conditionals)
+        .all(|when| match &when.reference {
+            Some(reference) => reference.reference().is_sibling(),
+            None => true,
+        });
+    resolvable.then_some(&alt.matches)
+}
+
+/// Every schema an alternatives value can end up matching
+pub fn alternative_options(alt: &JoiAlternatives) -> Vec<&JoiDescribe> {
+    let mut schemas: Vec<&JoiDescribe> = Vec::new();
+    for one_match in alt.matches.iter() {
+        let matched: Vec<&JoiDescribe> = match one_match {
+            AltSchema::Schema { schema } => vec![schema.as_ref()],
+            AltSchema::Conditional(when) => {
+                let cases = when.switch.iter().flatten();
+                let cases = cases.flat_map(|case| [case.then.as_ref(), case.otherwise.as_ref()]);
+                [when.then.as_ref(), when.otherwise.as_ref()]
+                    .into_iter()
+                    .chain(cases)
+                    .flatten()
+                    .collect()
+            }
+        };
+        for schema in matched {
+            if !schemas.contains(&schema) {
+                schemas.push(schema);
+            }
+        }
+    }
+    schemas
+}
+
+/// Everything an alternatives schema can match, combined according to its match mode.
+/// Conditional matches of an object key are enforced by the parent object's super refine, the
+/// others are reported by `Conversion::record`
+pub fn alternatives_schema(
+    alt: &JoiAlternatives,
+    schema: &JoiDescribe,
+    options: &Conversion,
+    out: &mut RuleTokens,
+) -> Result<js::Tokens, CodeGenError> {
+    let schemas = alternative_options(alt)
+        .into_iter()
+        .map(|schema| schema.to_tokens(false, options))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (first, rest) = match schemas.as_slice() {
+        [] => return Ok(quote! { z.never() }),
+        [schema] => return Ok(schema.clone()),
+        [first, rest @ ..] => (first, rest),
+    };
+    let schema = match schema.flags.r#match.as_deref() {
+        Some("all") => quote! { $first$(for schema in rest => .and($schema)) },
+        Some("one") => {
+            out.super_refines.push(quote! {
+                (val, ctx) => {
+                    if (val == null) {
+                        return;
+                    }
+                    const matched = [$(for schema in schemas.iter() join (, )=> $schema)].filter((schema) => schema.safeParse(val).success);
+                    if (matched.length > 1) {
+                        ctx.addIssue({ code: z.ZodIssueCode.custom, message: "Value matches more than one allowed type" });
+                    }
+                }
+            });
+            quote! { z.union([$(for schema in schemas.iter() join (, )=> $schema)]) }
+        }
+        _ => quote! { z.union([$(for schema in schemas.iter() join (, )=> $schema)]) },
+    };
+    Ok(schema)
+}
+
+/// A nested ternary picking the schema of an alternatives key, the first match that applies wins
+fn alternative_branches(
+    key: &str,
+    matches: &[AltSchema],
+    options: &Conversion,
+) -> Result<js::Tokens, CodeGenError> {
+    let Some((first, rest)) = matches.split_first() else {
+        return Ok(quote! { z.never() });
+    };
+    match first {
+        AltSchema::Schema { schema } => {
+            let schema = schema.to_tokens(true, options)?;
+            let rest = alternative_branches(key, rest, options)?;
+            Ok(
+                quote! {($(&schema).safeParse(val[$[str]($[const](key))]).success ? $schema : $rest)},
+            )
+        }
+        AltSchema::Conditional(when) => match &when.switch {
+            Some(cases) => alternative_switch(key, when, cases, rest, options),
+            None => {
+                let value = when_value(key, when);
+                let check = when_check(when, &value, options)?;
+                let then = alternative_branch(key, when.then.as_ref(), rest, options)?;
+                let otherwise = alternative_branch(key, when.otherwise.as_ref(), rest, options)?;
+                Ok(quote! {($check ? $then : $otherwise)})
+            }
+        },
+    }
+}
+
+/// The schema of a matched condition, or the following matches if the condition has none
+fn alternative_branch(
+    key: &str,
+    schema: Option<&JoiDescribe>,
+    rest: &[AltSchema],
+    options: &Conversion,
+) -> Result<js::Tokens, CodeGenError> {
+    match schema {
+        Some(schema) => schema.to_tokens(true, options),
+        None => alternative_branches(key, rest, options),
+    }
+}
+
+fn alternative_switch(
+    key: &str,
+    when: &JoiWhen,
+    cases: &[JoiWhenSwitch],
+    rest: &[AltSchema],
+    options: &Conversion,
+) -> Result<js::Tokens, CodeGenError> {
+    let Some((case, other_cases)) = cases.split_first() else {
+        return alternative_branches(key, rest, options);
+    };
+    let value = when_value(key, when);
+    let check = is_check(&case.is, &value, options)?;
+    let then = alternative_branch(key, case.then.as_ref(), rest, options)?;
+    let otherwise = match (&case.otherwise, other_cases.is_empty()) {
+        (Some(otherwise), true) => otherwise.to_tokens(true, options)?,
+        _ => alternative_switch(key, when, other_cases, rest, options)?,
+    };
+    Ok(quote! {($check ? $then : $otherwise)})
+}
+
+/// When every condition compares the same sibling key against literals, and that sibling only
+/// allows literals, the object can be split into a discriminated union with an option per value
+fn discriminated_union(
+    object: &JoiObject,
+    options: &Conversion,
+    make_object: &impl Fn(Vec<(&String, js::Tokens)>) -> js::Tokens,
+    out: &mut RuleTokens,
+) -> Result<Option<js::Tokens>, CodeGenError> {
+    let Some(discriminator) = discriminator_key(object) else {
+        return Ok(None);
+    };
+    let discriminator_schema = &object.keys[discriminator];
+    let Some(values) = literal_values(discriminator_schema) else {
+        return Ok(None);
+    };
+
+    // zod picks the option from the raw input so an optional discriminator needs a default
+    let default = discriminator_schema.flags.default.as_ref();
+    let required = discriminator_schema.flags.presence.as_deref() == Some("required");
+    if !required && default.is_none() {
+        return Ok(None);
+    }
+
+    let mut union_options = Vec::with_capacity(values.len());
+    for value in values.iter() {
+        let mut keys = Vec::with_capacity(object.keys.len());
+        for (key, schema) in object.keys.iter() {
+            let tokens = if key == discriminator {
+                let value = value.to_string();
+                quote! { z.literal($value) }
+            } else if let Some(whens) = &schema.whens {
+                let Some(branch) = resolve_branch(schema, whens, value) else {
+                    return Ok(None);
+                };
+                options.record_branch_whens(&branch);
+                branch.to_tokens(true, options)?
+            } else {
+                schema.to_tokens(true, options)?
+            };
+            keys.push((key, tokens));
+        }
+        union_options.push(make_object(keys));
+    }
+
+    if let (Some(default), false) = (default, required) {
+        let default = default.to_string();
+        out.pre_processes.push(quote! {
+            (val) => {
+                if (val && typeof val === "object" && val[$[str]($[const](discriminator))] === undefined) {
+                    return { ...val, [$[str]($[const](discriminator))]: $default };
+                }
+                return val;
+            }
+        });
+    }
+
+    Ok(Some(quote! {
+        z.discriminatedUnion($[str]($[const](discriminator)), [
+            $(for option in union_options join (,$['\r'])=> $option)
+        ])
+    }))
+}
+
+/// The sibling key every condition of an object compares against literals, if there is one
+fn discriminator_key(object: &JoiObject) -> Option<&String> {
+    let mut discriminator: Option<&String> = None;
+    for schema in object.keys.values() {
+        let Some(whens) = &schema.whens else {
+            continue;
+        };
+        for when in whens {
+            let path = &when.reference.as_ref()?.reference().path;
+            let [key] = path.as_slice() else {
+                return None;
+            };
+            if *discriminator.get_or_insert(key) != key || when.switch.is_some() {
+                return None;
+            }
+            literal_values(when_test(when)?.0)?;
+        }
+    }
+    let discriminator = discriminator?;
+    let discriminator_schema = object.keys.get(discriminator)?;
+    if discriminator_schema.whens.is_some() {
+        return None;
+    }
+    Some(discriminator)
+}
+
+/// Build an object schema, handling the conditions on its keys
+pub fn object_schema(
+    object: &JoiObject,
+    options: &Conversion,
+    make_object: impl Fn(Vec<(&String, js::Tokens)>) -> js::Tokens,
+    out: &mut RuleTokens,
+) -> Result<js::Tokens, CodeGenError> {
+    let conditional: Vec<(&String, &JoiDescribe, &[JoiWhen])> = object
+        .keys
+        .iter()
+        .filter_map(|(key, schema)| object_whens(schema).map(|whens| (key, schema, whens)))
+        .collect();
+
+    let alternatives: Vec<(&String, &[AltSchema])> = object
+        .keys
+        .iter()
+        .filter_map(|(key, schema)| object_alternatives(schema).map(|matches| (key, matches)))
+        .collect();
+
+    if conditional.is_empty() && alternatives.is_empty() {
+        let keys = object
+            .keys
+            .iter()
+            .map(|(key, value)| Ok((key, value.to_tokens(true, options)?)))
+            .collect::<Result<_, CodeGenError>>()?;
+        return Ok(make_object(keys));
+    }
+
+    if alternatives.is_empty()
+        && conditional.len() == object.keys.values().filter(|v| v.whens.is_some()).count()
+    {
+        if let Some(union) = discriminated_union(object, options, &make_object, out)? {
+            return Ok(union);
+        }
+    }
+
+    let checks = conditional.iter().map(|(key, schema, whens)| {
+        let mut base = (*schema).clone();
+        base.whens = None;
+        let branch = branches(key, &base, whens, options)?;
+        Ok(quote! {
+            check($[str]($[const](key.as_str())), $branch);
+        })
+    });
+    let alternative_checks = alternatives.iter().map(|(key, matches)| {
+        let branch = alternative_branches(key, matches, options)?;
+        Ok(quote! {
+            check($[str]($[const](key.as_str())), $branch);
+        })
+    });
+    let checks = checks
+        .chain(alternative_checks)
+        .collect::<Result<Vec<js::Tokens>, CodeGenError>>()?;
+    out.super_refines.push(quote! {
+        (val, ctx) => {
+            if (val == null) {
+                return;
+            }
+            const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+            const check = (key, schema) => {
+                const result = schema.safeParse(val[key]);
+                if (!result.success) {
+                    result.error.issues.forEach((issue) => ctx.addIssue({ ...issue, path: [key, ...issue.path] }));
+                }
+            };
+            $(for check in checks join ($['\r'])=> $check)
+        }
+    });
+
+    let keys = object
+        .keys
+        .iter()
+        .map(|(key, value)| {
+            let tokens = match object_whens(value) {
+                Some(_) => loose_key(value, options)?,
+                None => value.to_tokens(true, options)?,
+            };
+            Ok((key, tokens))
+        })
+        .collect::<Result<_, CodeGenError>>()?;
+    Ok(make_object(keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::joi::JoiDescribe;
+
+    fn convert(describe: &str) -> String {
+        let joi: JoiDescribe = serde_json::from_str(describe).expect("should work...");
+        joi.convert().unwrap()
+    }
+
+    /// `ObjetWithWhen` from the node-package examples
+    const OBJECT_WITH_WHEN: &str = r#"{
+        "type": "object",
+        "flags": { "presence": "required" },
+        "keys": {
+            "condVal": {
+                "type": "string",
+                "flags": { "default": "foo", "only": true },
+                "allow": ["foo", "bar"]
+            },
+            "username": {
+                "type": "string",
+                "whens": [{
+                    "ref": { "path": ["condVal"] },
+                    "not": { "type": "any", "flags": { "only": true, "presence": "required" }, "allow": ["bar"] },
+                    "then": { "type": "any", "flags": { "presence": "required" } },
+                    "otherwise": { "type": "any", "flags": { "presence": "optional" } }
+                }]
+            },
+            "password": {
+                "type": "string",
+                "whens": [{
+                    "ref": { "path": ["condVal"] },
+                    "is": { "type": "any", "flags": { "only": true, "presence": "required" }, "allow": ["bar"] },
+                    "then": { "type": "any", "flags": { "presence": "required" } },
+                    "otherwise": { "type": "any", "flags": { "presence": "forbidden" } }
+                }]
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_when_discriminated_union() {
+        assert_eq!(
+            convert(OBJECT_WITH_WHEN),
+            r#"z.preprocess((val) => {
+    if (val && typeof val === "object" && val["condVal"] === undefined) {
+        return { ...val, ["condVal"]: "foo" };
+    }
+    return val;
+}, z.discriminatedUnion("condVal", [
+    z.object({
+        condVal: z.literal("foo"),
+        password: z.string().undefined(),
+        username: z.string()
+    }),
+    z.object({
+        condVal: z.literal("bar"),
+        password: z.string(),
+        username: z.string().optional()
+    })
+]))"#
+        );
+    }
+
+    #[test]
+    fn test_when_super_refine() {
+        // condVal is not limited to literals so a discriminated union can't be used
+        let describe = OBJECT_WITH_WHEN.replace(r#""only": true },"#, r#""only": false },"#);
+        assert_eq!(
+            convert(&describe),
+            r#"z.object({
+    condVal: z.string().default("foo"),
+    password: z.string().optional(),
+    username: z.string().optional()
+}).superRefine((val, ctx) => {
+    if (val == null) {
+        return;
+    }
+    const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+    const check = (key, schema) => {
+        const result = schema.safeParse(val[key]);
+        if (!result.success) {
+            result.error.issues.forEach((issue) => ctx.addIssue({ ...issue, path: [key, ...issue.path] }));
+        }
+    };
+    check("password", (["bar"].includes(resolve(["condVal"])) ? z.string() : z.string().undefined()));
+    check("username", (!(["bar"].includes(resolve(["condVal"]))) ? z.string() : z.string().optional()));
+})"#
+        );
+    }
+
+    #[test]
+    fn test_when_switch_on_own_value() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "keys": {
+                        "a": {
+                            "type": "number",
+                            "whens": [{
+                                "switch": [
+                                    { "is": { "type": "number", "rules": [{ "name": "greater", "args": { "limit": 10 } }] }, "then": { "type": "any", "rules": [{ "name": "integer" }] } },
+                                    { "is": { "type": "number", "flags": { "only": true, "presence": "required" }, "allow": [1, 2] }, "then": { "type": "any", "flags": { "presence": "required" } }, "otherwise": { "type": "any", "flags": { "presence": "forbidden" } } }
+                                ]
+                            }]
+                        }
+                    }
+                }"#
+            ),
+            r#"z.object({
+    a: z.number().optional()
+}).superRefine((val, ctx) => {
+    if (val == null) {
+        return;
+    }
+    const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+    const check = (key, schema) => {
+        const result = schema.safeParse(val[key]);
+        if (!result.success) {
+            result.error.issues.forEach((issue) => ctx.addIssue({ ...issue, path: [key, ...issue.path] }));
+        }
+    };
+    check("a", (z.number().gt(10).optional().safeParse(val["a"]).success ? z.number().int().optional() : ([1, 2].includes(val["a"]) ? z.number() : z.number().undefined())));
+})"#
+        );
+    }
+
+    #[test]
+    fn test_alternatives_conditional() {
+        // `AlternativesConditionalSchema` from the node-package examples
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "metas": [{ "className": "SomeSchema" }],
+                    "keys": {
+                        "label": { "type": "string" },
+                        "someId": {
+                            "type": "alternatives",
+                            "matches": [{
+                                "ref": { "path": ["label"] },
+                                "is": { "type": "any", "flags": { "only": true, "presence": "required" }, "allow": ["abc"] },
+                                "then": {
+                                    "type": "string",
+                                    "flags": { "presence": "required" },
+                                    "rules": [{ "name": "hex", "args": { "options": { "byteAligned": false } } }, { "name": "length", "args": { "limit": 24 } }]
+                                },
+                                "otherwise": { "type": "any", "flags": { "presence": "forbidden" } }
+                            }]
+                        }
+                    }
+                }"#
+            ),
+            r#"z.object({
+    label: z.string().optional(),
+    someId: z.union([z.string().regex(/^[a-f0-9]+$/i, { message: "Must only contain hexadecimal characters" }).length(24), z.any().undefined()]).optional()
+}).superRefine((val, ctx) => {
+    if (val == null) {
+        return;
+    }
+    const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+    const check = (key, schema) => {
+        const result = schema.safeParse(val[key]);
+        if (!result.success) {
+            result.error.issues.forEach((issue) => ctx.addIssue({ ...issue, path: [key, ...issue.path] }));
+        }
+    };
+    check("someId", (["abc"].includes(resolve(["label"])) ? z.string().regex(/^[a-f0-9]+$/i, { message: "Must only contain hexadecimal characters" }).length(24) : z.any().undefined()));
+})"#
+        );
+    }
+
+    #[test]
+    fn test_when_unresolvable_ref() {
+        // context references can't be seen by zod so the condition is left alone
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "keys": {
+                        "a": {
+                            "type": "string",
+                            "whens": [{
+                                "ref": { "ref": { "path": ["flag"], "type": "global" } },
+                                "is": { "type": "boolean", "flags": { "presence": "required" } },
+                                "then": { "type": "any", "flags": { "presence": "required" } }
+                            }]
+                        }
+                    }
+                }"#
+            ),
+            "z.object({\n    a: z.string().optional()\n})"
+        );
+    }
+}
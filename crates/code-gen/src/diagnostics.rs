@@ -0,0 +1,711 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+
+use genco::prelude::js;
+use genco::prelude::*;
+use miette::Severity;
+
+use crate::conditions;
+use crate::joi::JoiDescribe;
+use crate::joi_types::{AltSchema, JoiDescribeType};
+use crate::links;
+use crate::refs;
+use crate::rules;
+use crate::GenOptions;
+
+/// What could not be converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// A custom type with no converter, from `Joi.extend()`
+    UnsupportedType,
+    /// A rule with no zod equivalent
+    UnsupportedRule,
+    /// A flag with no zod equivalent, like `strip()`
+    UnsupportedFlag,
+    /// A `when()` condition the schema is converted without
+    UnsupportedCondition,
+    /// `meta()` values, which zod has no place for
+    DroppedMetas,
+    /// An object dependency that can't be checked, like `with()` without its main key
+    UnsupportedDependency,
+    /// A reference to the `$context`, or to a value outside of the objects the schema is in
+    UnresolvedReference,
+    /// A link to something other than a schema id or the root schema
+    UnresolvedLink,
+    /// `instance()` of a class without a name that can be used in the generated code
+    UnnamedInstance,
+    /// A rule converted without some of what joi does, like the `tlds` check of `email()`
+    PartialRule,
+}
+
+impl DiagnosticKind {
+    /// The code of the diagnostic, like `joi_to_zod::unsupported_rule`
+    pub fn code(&self) -> &'static str {
+        match self {
+            DiagnosticKind::UnsupportedType => "joi_to_zod::unsupported_type",
+            DiagnosticKind::UnsupportedRule => "joi_to_zod::unsupported_rule",
+            DiagnosticKind::UnsupportedFlag => "joi_to_zod::unsupported_flag",
+            DiagnosticKind::UnsupportedCondition => "joi_to_zod::unsupported_condition",
+            DiagnosticKind::DroppedMetas => "joi_to_zod::dropped_metas",
+            DiagnosticKind::UnsupportedDependency => "joi_to_zod::unsupported_dependency",
+            DiagnosticKind::UnresolvedReference => "joi_to_zod::unresolved_reference",
+            DiagnosticKind::UnresolvedLink => "joi_to_zod::unresolved_link",
+            DiagnosticKind::UnnamedInstance => "joi_to_zod::unnamed_instance",
+            DiagnosticKind::PartialRule => "joi_to_zod::partial_rule",
+        }
+    }
+}
+
+/// A part of the joi schema left out of the generated zod schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    /// JSON path to the schema in the describe output, like `$.keys.name`
+    pub path: String,
+    /// The name of the joi type, rule or flag
+    pub name: String,
+    /// How to convert it by hand
+    pub suggestion: String,
+}
+
+impl Diagnostic {
+    fn new(kind: DiagnosticKind, name: &str, suggestion: &str) -> Self {
+        Diagnostic {
+            kind,
+            path: String::new(),
+            name: name.to_string(),
+            suggestion: suggestion.to_string(),
+        }
+    }
+
+    /// What is left out, without where
+    fn message(&self) -> String {
+        let what = match self.kind {
+            DiagnosticKind::UnsupportedType => "type",
+            DiagnosticKind::UnsupportedRule => "rule",
+            DiagnosticKind::UnsupportedFlag => "flag",
+            DiagnosticKind::UnsupportedCondition => "condition",
+            DiagnosticKind::UnsupportedDependency => "dependency",
+            DiagnosticKind::DroppedMetas => return format!("dropped joi `{}` values", self.name),
+            DiagnosticKind::UnresolvedReference => {
+                return format!("unresolved joi reference `{}`", self.name)
+            }
+            DiagnosticKind::UnresolvedLink => {
+                return format!("unresolved joi link `{}`", self.name)
+            }
+            DiagnosticKind::UnnamedInstance => {
+                return format!("joi `{}` of a class without a name", self.name)
+            }
+            DiagnosticKind::PartialRule => {
+                return format!("partly converted joi rule `{}`", self.name)
+            }
+        };
+        format!("unsupported joi {} `{}`", what, self.name)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message(), self.path)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl miette::Diagnostic for Diagnostic {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.kind.code()))
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        Some(Severity::Warning)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(&self.suggestion))
+    }
+}
+
+/// A rule with no zod equivalent, found while converting the rules of a schema
+pub fn unsupported_rule(name: &str) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticKind::UnsupportedRule,
+        name,
+        "Register a converter for the rule, or add a `refine()` doing the same check",
+    )
+}
+
+/// A rule converted without some of what joi does, `suggestion` says what and how to do it by
+/// hand
+pub fn partial_rule(name: &str, suggestion: &str) -> Diagnostic {
+    Diagnostic::new(DiagnosticKind::PartialRule, name, suggestion)
+}
+
+/// What of a single schema can't be converted, without paths. `rules` is what the conversion of
+/// the rules left out
+fn unsupported(
+    schema: &JoiDescribe,
+    options: &GenOptions,
+    rules: Vec<Diagnostic>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if let JoiDescribeType::Unknown(unknown) = &schema.type_options {
+        if !options.extensions.has_type(&unknown.joi_type) {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticKind::UnsupportedType,
+                &unknown.joi_type,
+                "Register a converter for the type, it is converted to `z.any()` until then",
+            ));
+        }
+    }
+
+    if let JoiDescribeType::Object(object) = &schema.type_options {
+        let dependencies = object.dependencies.iter();
+        for dep in dependencies.filter(|dep| !rules::is_supported_dependency(dep)) {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticKind::UnsupportedDependency,
+                &dep.rel,
+                "Check the keys with a `superRefine()` on the object",
+            ));
+        }
+    }
+
+    if let JoiDescribeType::Link(link) = &schema.type_options {
+        if let Some(link) = links::unresolved_link(link) {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticKind::UnresolvedLink,
+                &link,
+                "Only links to a schema id or the root are converted, it is converted to `z.any()` until then",
+            ));
+        }
+    }
+
+    if rules::is_instance(schema) && rules::instance_of(schema).is_none() {
+        diagnostics.push(Diagnostic::new(
+            DiagnosticKind::UnnamedInstance,
+            "instance",
+            "Use a named class, it is converted to `z.any()` until then",
+        ));
+    }
+
+    // joi turns the values of the map into its symbols, which the describe output can't hold
+    if let JoiDescribeType::Symbol(symbol) = &schema.type_options {
+        if !symbol.map.is_empty() {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticKind::UnsupportedRule,
+                "map",
+                "Turn the values into the same symbols with a `transform()`, any symbol and the values of the map are accepted until then",
+            ));
+        }
+    }
+
+    for reference in refs::schema_refs(schema) {
+        if reference.ancestor_level().is_none() {
+            diagnostics.push(unresolved_reference(&reference.display()));
+        }
+    }
+
+    diagnostics.extend(rules);
+
+    if schema.flags.result.as_deref() == Some("strip") {
+        diagnostics.push(Diagnostic::new(
+            DiagnosticKind::UnsupportedFlag,
+            "strip",
+            "Remove the key from the parsed value, like with a `transform()` on the parent object",
+        ));
+    }
+
+    if schema.whens.iter().flatten().next().is_some() {
+        diagnostics.push(Diagnostic::new(
+            DiagnosticKind::UnsupportedCondition,
+            "when",
+            "Only conditions on sibling keys are converted, check the condition with a `superRefine()` on the parent object",
+        ));
+    }
+
+    // a module is named by the `className` meta
+    let dropped_metas = schema.metas.iter().any(|meta| {
+        meta.keys()
+            .any(|key| options.module.is_none() || key != "className")
+    });
+    if dropped_metas {
+        diagnostics.push(Diagnostic::new(
+            DiagnosticKind::DroppedMetas,
+            "meta",
+            "Keep the values next to the zod schema if they are needed, like in a `describe()`",
+        ));
+    }
+
+    diagnostics
+}
+
+fn unresolved_reference(reference: &str) -> Diagnostic {
+    Diagnostic::new(
+        DiagnosticKind::UnresolvedReference,
+        reference,
+        "Zod only sees the value being parsed, check the referenced value with a `superRefine()` where it is known",
+    )
+}
+
+/// Converting schemas: the options, and what of the schemas is left out, recorded as each
+/// schema is converted
+pub struct Conversion<'a> {
+    options: &'a GenOptions,
+    /// What each schema can't convert because of the schemas it is nested in, by its JSON path,
+    /// in the order the schemas are nested
+    scopes: Vec<(String, Scoped)>,
+    diagnostics: RefCell<Vec<Diagnostic>>,
+}
+
+/// What of a schema can't be converted because of the schemas it is nested in
+#[derive(Default)]
+struct Scoped {
+    diagnostics: Vec<Diagnostic>,
+    /// If the `when()` conditions of the schema are converted by the object it is a key of
+    whens_handled: bool,
+}
+
+impl<'a> Conversion<'a> {
+    /// Start converting `schemas`, which have their paths set with `JoiDescribe::set_paths()`
+    pub fn new(options: &'a GenOptions, schemas: &[&JoiDescribe]) -> Self {
+        let mut scopes = Vec::new();
+        for schema in schemas {
+            let scope = Scope {
+                levels: 0,
+                undeclared: &links::undeclared_ids(schema),
+                whens_handled: false,
+                alternatives_handled: false,
+            };
+            scoped_at(schema, &scope, &mut scopes);
+        }
+        Conversion {
+            options,
+            scopes,
+            diagnostics: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Record what of a converted schema is left out. `rules` is what the conversion of the rules
+    /// left out. Returns the diagnostics of the schema
+    pub fn record(&self, schema: &JoiDescribe, rules: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let mut diagnostics = unsupported(schema, self.options, rules);
+        if let Some((_, scoped)) = self.scopes.iter().find(|(path, _)| *path == schema.path) {
+            if scoped.whens_handled {
+                diagnostics
+                    .retain(|diagnostic| diagnostic.kind != DiagnosticKind::UnsupportedCondition);
+            }
+            diagnostics.extend(scoped.diagnostics.iter().cloned());
+        }
+        let diagnostics: Vec<Diagnostic> = diagnostics
+            .into_iter()
+            .map(|diagnostic| Diagnostic {
+                path: schema.path.clone(),
+                ..diagnostic
+            })
+            .collect();
+
+        // a schema can be converted more than once, like a key with conditions
+        let mut recorded = self.diagnostics.borrow_mut();
+        for diagnostic in &diagnostics {
+            if !recorded.contains(diagnostic) {
+                recorded.push(diagnostic.clone());
+            }
+        }
+        diagnostics
+    }
+
+    /// Record the conditions of a branch of a key's conditions, the object only converts the
+    /// conditions of the key itself
+    pub fn record_branch_whens(&self, branch: &JoiDescribe) {
+        if branch.whens.iter().flatten().next().is_none() {
+            return;
+        }
+        let diagnostic = Diagnostic {
+            path: branch.path.clone(),
+            ..Diagnostic::new(
+                DiagnosticKind::UnsupportedCondition,
+                "when",
+                "Conditions in the `then` or `otherwise` of a key's condition aren't converted, move them to the key",
+            )
+        };
+        let mut recorded = self.diagnostics.borrow_mut();
+        if !recorded.contains(&diagnostic) {
+            recorded.push(diagnostic);
+        }
+    }
+
+    /// What of the converted schemas is left out, in the order the schemas are nested
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        // schemas not nested like the ones in a `has()` rule go with the schema they are in
+        let position = |path: &str| {
+            self.scopes
+                .iter()
+                .enumerate()
+                .filter(|(_, (scope_path, _))| path.starts_with(scope_path.as_str()))
+                .max_by_key(|(_, (scope_path, _))| scope_path.len())
+                .map(|(index, _)| index)
+        };
+        let mut diagnostics = self.diagnostics.take();
+        diagnostics.sort_by_key(|diagnostic| position(&diagnostic.path));
+        diagnostics
+    }
+}
+
+impl Deref for Conversion<'_> {
+    type Target = GenOptions;
+
+    fn deref(&self) -> &GenOptions {
+        self.options
+    }
+}
+
+/// What a schema can use from the schemas it is nested in
+struct Scope<'a> {
+    /// How many objects the schema is a key of, a reference can only be checked by one of them
+    levels: u64,
+    /// The ids links point at that are never declared
+    undeclared: &'a [String],
+    /// If the `when()` conditions of the schema are converted by the object it is a key of
+    whens_handled: bool,
+    /// If the conditional matches of an alternatives schema are enforced by the object it is a
+    /// key of
+    alternatives_handled: bool,
+}
+
+fn scoped_at(schema: &JoiDescribe, scope: &Scope, out: &mut Vec<(String, Scoped)>) {
+    let mut scoped = Scoped {
+        diagnostics: Vec::new(),
+        whens_handled: scope.whens_handled,
+    };
+    for reference in refs::schema_refs(schema) {
+        if reference
+            .ancestor_level()
+            .is_some_and(|level| level > scope.levels)
+        {
+            scoped
+                .diagnostics
+                .push(unresolved_reference(&reference.display()));
+        }
+    }
+    if let JoiDescribeType::Link(link) = &schema.type_options {
+        if let Some(id) = links::linked_id(link).filter(|id| scope.undeclared.contains(id)) {
+            scoped.diagnostics.push(Diagnostic::new(
+                DiagnosticKind::UnresolvedLink,
+                &format!("#{}", id),
+                "No schema has the id, it is converted to `z.any()` until then",
+            ));
+        }
+    }
+    if let JoiDescribeType::Alternatives(alt) = &schema.type_options {
+        let conditional = alt
+            .matches
+            .iter()
+            .any(|one_match| matches!(one_match, AltSchema::Conditional(_)));
+        if conditional && !scope.alternatives_handled {
+            scoped.diagnostics.push(Diagnostic::new(
+                DiagnosticKind::UnsupportedCondition,
+                "conditional",
+                "Only conditional alternatives of an object key on its siblings are enforced, it is converted to a union of every branch until then",
+            ));
+        }
+    }
+    out.push((schema.path.clone(), scoped));
+
+    // the conditions of an object's keys are converted by the object
+    let keys: Vec<&JoiDescribe> = match &schema.type_options {
+        JoiDescribeType::Object(object) => object.keys.values().collect(),
+        _ => Vec::new(),
+    };
+    for (_, child) in schema.children() {
+        let is_key = keys.iter().any(|key| std::ptr::eq(*key, child));
+        let child_scope = Scope {
+            levels: match is_key {
+                true => scope.levels + 1,
+                false => 0,
+            },
+            undeclared: scope.undeclared,
+            whens_handled: is_key && conditions::object_whens(child).is_some(),
+            alternatives_handled: is_key && conditions::object_alternatives(child).is_some(),
+        };
+        scoped_at(child, &child_scope, out);
+    }
+}
+
+/// Put a `// TODO` comment before a schema for each of its diagnostics
+pub fn with_todo_comments(diagnostics: &[Diagnostic], tokens: js::Tokens) -> js::Tokens {
+    if diagnostics.is_empty() {
+        return tokens;
+    }
+    let comments = diagnostics
+        .iter()
+        .map(|diagnostic| format!("// TODO: {}", diagnostic.message()));
+    quote! {
+        $(for comment in comments => $comment$['\r'])
+        $tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiagnosticKind;
+    use crate::{gen_with_diagnostics, GenOptions};
+
+    const DESCRIBE: &str = r#"{
+        "type": "object",
+        "keys": {
+            "money": { "type": "money" },
+            "name": {
+                "type": "string",
+                "flags": { "result": "strip" },
+                "rules": [{ "name": "slug" }, { "name": "max", "args": { "limit": 10 } }]
+            },
+            "kind": { "type": "string", "metas": [{ "label": "Kind" }] },
+            "size": {
+                "type": "number",
+                "whens": [{ "ref": { "path": ["kind"] }, "is": { "type": "any", "flags": { "only": true }, "allow": ["big"] }, "then": { "type": "any", "flags": { "presence": "required" } } }]
+            }
+        },
+        "whens": [{ "ref": { "path": ["mode"], "type": "global" }, "is": { "type": "any" }, "then": { "type": "any", "flags": { "presence": "required" } } }]
+    }"#;
+
+    #[test]
+    fn test_diagnostics() {
+        let output = gen_with_diagnostics(DESCRIBE.to_string(), &GenOptions::default()).unwrap();
+        let found: Vec<(DiagnosticKind, &str, &str)> = output
+            .diagnostics
+            .iter()
+            .map(|d| (d.kind, d.path.as_str(), d.name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (DiagnosticKind::UnsupportedCondition, "$", "when"),
+                (DiagnosticKind::DroppedMetas, "$.keys.kind", "meta"),
+                (DiagnosticKind::UnsupportedType, "$.keys.money", "money"),
+                (DiagnosticKind::UnsupportedRule, "$.keys.name", "slug"),
+                (DiagnosticKind::UnsupportedFlag, "$.keys.name", "strip"),
+            ]
+        );
+        assert_eq!(
+            output.diagnostics[3].to_string(),
+            "unsupported joi rule `slug` at $.keys.name"
+        );
+        assert!(!output.code.contains("__please"));
+    }
+
+    #[test]
+    fn test_todo_comments() {
+        let options = GenOptions {
+            todo_comments: true,
+            ..GenOptions::default()
+        };
+        let output = gen_with_diagnostics(DESCRIBE.to_string(), &options).unwrap();
+        assert_eq!(
+            output.code,
+            r#"// TODO: unsupported joi condition `when`
+z.object({
+    kind: // TODO: dropped joi `meta` values
+    z.string().optional(),
+    money: // TODO: unsupported joi type `money`
+    z.any().optional(),
+    name: // TODO: unsupported joi rule `slug`
+    // TODO: unsupported joi flag `strip`
+    z.string().max(10).optional(),
+    size: z.number().optional()
+}).optional().superRefine((val, ctx) => {
+    if (val == null) {
+        return;
+    }
+    const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+    const check = (key, schema) => {
+        const result = schema.safeParse(val[key]);
+        if (!result.success) {
+            result.error.issues.forEach((issue) => ctx.addIssue({ ...issue, path: [key, ...issue.path] }));
+        }
+    };
+    check("size", ((resolve(["kind"]) === undefined || ["big"].includes(resolve(["kind"]))) ? z.number() : z.number().optional()));
+})"#
+        );
+    }
+
+    #[test]
+    fn test_unresolved_placeholders() {
+        let describe = r#"{
+            "type": "object",
+            "keys": {
+                "parent": { "type": "link", "link": { "ref": { "path": ["a", "b"], "type": "local" } } },
+                "value": { "type": "object", "rules": [{ "name": "instance", "args": { "name": "" } }] }
+            }
+        }"#;
+        let output = gen_with_diagnostics(describe.to_string(), &GenOptions::default()).unwrap();
+        let found: Vec<(DiagnosticKind, &str, &str)> = output
+            .diagnostics
+            .iter()
+            .map(|d| (d.kind, d.path.as_str(), d.name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (DiagnosticKind::UnresolvedLink, "$.keys.parent", "a.b"),
+                (DiagnosticKind::UnnamedInstance, "$.keys.value", "instance"),
+            ]
+        );
+        assert_eq!(
+            output.code,
+            "z.object({\n    parent: z.any().optional(),\n    value: z.any().optional()\n}).optional()"
+        );
+    }
+
+    #[test]
+    fn test_conditional_alternatives() {
+        let alternatives = r#"{
+            "type": "alternatives",
+            "matches": [{
+                "ref": { "path": ["kind"] },
+                "is": { "type": "any", "flags": { "only": true }, "allow": ["a"] },
+                "then": { "type": "string" },
+                "otherwise": { "type": "number" }
+            }]
+        }"#;
+        let describe = format!(
+            r#"{{ "type": "object", "keys": {{ "kind": {{ "type": "string" }}, "value": {alternatives}, "list": {{ "type": "array", "items": [{alternatives}] }} }} }}"#
+        );
+        let output = gen_with_diagnostics(describe, &GenOptions::default()).unwrap();
+        let found: Vec<(DiagnosticKind, &str, &str)> = output
+            .diagnostics
+            .iter()
+            .map(|d| (d.kind, d.path.as_str(), d.name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(
+                DiagnosticKind::UnsupportedCondition,
+                "$.keys.list.items[0]",
+                "conditional"
+            )]
+        );
+
+        // the diagnostics the conversion records get a comment too
+        let options = GenOptions {
+            todo_comments: true,
+            ..GenOptions::default()
+        };
+        let describe = format!(r#"{{ "type": "array", "items": [{alternatives}] }}"#);
+        let output = gen_with_diagnostics(describe, &options).unwrap();
+        assert_eq!(
+            output.code,
+            r#"z.array(// TODO: unsupported joi condition `conditional`
+z.union([z.string(), z.number()])).optional()"#
+        );
+    }
+
+    #[test]
+    fn test_strict() {
+        let options = GenOptions {
+            strict: true,
+            ..GenOptions::default()
+        };
+        let error = gen_with_diagnostics(DESCRIBE.to_string(), &options).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"the joi schema can't be converted exactly:
+  - unsupported joi condition `when` at $
+  - dropped joi `meta` values at $.keys.kind
+  - unsupported joi type `money` at $.keys.money
+  - unsupported joi rule `slug` at $.keys.name
+  - unsupported joi flag `strip` at $.keys.name"#
+        );
+        let exact =
+            r#"{ "type": "string", "rules": [{ "name": "max", "args": { "limit": 10 } }] }"#;
+        let output = gen_with_diagnostics(exact.to_string(), &options).unwrap();
+        assert_eq!(output.code, "z.string().max(10).optional()");
+        let exact = r#"{ "type": "string", "rules": [{ "name": "email", "args": { "options": { "tlds": { "allow": false }, "allowUnicode": false } } }] }"#;
+        assert!(gen_with_diagnostics(exact.to_string(), &options).is_ok());
+    }
+
+    #[test]
+    fn test_strict_lossy_mappings() {
+        let options = GenOptions {
+            strict: true,
+            ..GenOptions::default()
+        };
+        let cases = [
+            (
+                r#"{ "type": "number", "rules": [{ "name": "max", "args": { "limit": { "ref": { "path": ["max"], "type": "global" } } } }] }"#,
+                "unresolved joi reference `$max` at $",
+            ),
+            (
+                r#"{ "type": "number", "rules": [{ "name": "multiple", "args": { "base": { "ref": { "path": ["a"] } } } }] }"#,
+                "unresolved joi reference `a` at $",
+            ),
+            (
+                r#"{ "type": "object", "keys": { "a": { "type": "number" }, "b": { "type": "number", "rules": [{ "name": "precision", "args": { "limit": { "ref": { "path": ["a"] } } } }] } } }"#,
+                "unsupported joi rule `precision` at $.keys.b",
+            ),
+            (
+                r#"{ "type": "link", "link": { "ref": { "path": ["nope"], "type": "local" } } }"#,
+                "unresolved joi link `#nope` at $",
+            ),
+            (
+                r#"{ "type": "link", "link": { "ref": { "path": ["a", "b"], "type": "local" } } }"#,
+                "unresolved joi link `a.b` at $",
+            ),
+            (
+                r#"{ "type": "object", "rules": [{ "name": "instance", "args": { "name": "" } }] }"#,
+                "joi `instance` of a class without a name at $",
+            ),
+            (
+                r#"{ "type": "alternatives", "matches": [{ "ref": { "path": ["a"] }, "is": { "type": "any" }, "then": { "type": "string" }, "otherwise": { "type": "number" } }] }"#,
+                "unsupported joi condition `conditional` at $",
+            ),
+            (
+                r#"{ "type": "object", "dependencies": [{ "rel": "with", "key": null, "peers": ["a"] }] }"#,
+                "unsupported joi dependency `with` at $",
+            ),
+            (
+                r#"{ "type": "object", "dependencies": [{ "rel": "without", "peers": ["a"] }] }"#,
+                "unsupported joi dependency `without` at $",
+            ),
+            (
+                r#"{ "type": "symbol", "map": [["a", null]] }"#,
+                "unsupported joi rule `map` at $",
+            ),
+            (
+                r#"{ "type": "string", "rules": [{ "name": "email", "args": { "options": {} } }] }"#,
+                "partly converted joi rule `email` at $",
+            ),
+            (
+                r#"{ "type": "string", "rules": [{ "name": "email", "args": { "options": { "tlds": false, "allowUnicode": false, "minDomainSegments": 3 } } }] }"#,
+                "partly converted joi rule `email` at $",
+            ),
+            (
+                r#"{ "type": "string", "rules": [{ "name": "uri", "args": { "options": { "relativeOnly": true } } }] }"#,
+                "partly converted joi rule `uri` at $",
+            ),
+            (
+                r#"{ "type": "string", "rules": [{ "name": "ip", "args": { "options": { "version": ["ipv4", "ipvfuture"] } } }] }"#,
+                "partly converted joi rule `ip` at $",
+            ),
+            (
+                r#"{ "type": "string", "rules": [{ "name": "isoDate" }] }"#,
+                "partly converted joi rule `isoDate` at $",
+            ),
+            (
+                r#"{ "type": "object", "keys": { "a": { "type": "string" }, "b": { "type": "string", "whens": [{ "ref": { "path": ["a"] }, "is": { "type": "any", "flags": { "only": true }, "allow": ["x"] }, "then": { "type": "any", "whens": [{ "ref": { "path": ["c"], "type": "global" }, "is": { "type": "any" }, "then": { "type": "any", "flags": { "presence": "required" } } }] } }] } } }"#,
+                "unsupported joi condition `when` at $.keys.b",
+            ),
+        ];
+        for (describe, diagnostic) in cases {
+            let error = gen_with_diagnostics(describe.to_string(), &options).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "the joi schema can't be converted exactly:\n  - {}",
+                    diagnostic
+                )
+            );
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use genco::prelude::js;
+use genco::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+// https://joi.dev/api/?v=17.9.1#extensions
+
+/// Builds zod code from the described schema (for a type) or the rule arguments (for a rule)
+pub type ExtensionHandler = Arc<dyn Fn(&Value) -> js::Tokens + Send + Sync>;
+
+/// How a custom joi type or rule is converted
+#[derive(Clone)]
+pub enum Extension {
+    /// zod code where `{path}` is replaced with the JSON of the value at the dotted path, like
+    /// `{flags.default}` in the described schema or `{limit}` in the rule arguments. A path with
+    /// no value is replaced with `undefined`
+    Template(String),
+    /// A function building the zod code
+    Handler(ExtensionHandler),
+}
+
+impl fmt::Debug for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Extension::Template(template) => f.debug_tuple("Template").field(template).finish(),
+            Extension::Handler(_) => f.debug_tuple("Handler").finish(),
+        }
+    }
+}
+
+impl Extension {
+    /// A handler from a function
+    pub fn handler(handler: impl Fn(&Value) -> js::Tokens + Send + Sync + 'static) -> Self {
+        Extension::Handler(Arc::new(handler))
+    }
+
+    fn to_tokens(&self, value: &Value) -> js::Tokens {
+        match self {
+            Extension::Template(template) => {
+                let code = fill_template(template, value);
+                quote! { $code }
+            }
+            Extension::Handler(handler) => handler(value),
+        }
+    }
+}
+
+/// Replace the `{path}` placeholders of a template
+fn fill_template(template: &str, value: &Value) -> String {
+    let mut code = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let path = &rest[start + 1..start + end];
+        let is_path = !path.is_empty()
+            && path
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '.');
+        code.push_str(&rest[..start]);
+        if is_path {
+            let pointer = format!("/{}", path.replace('.', "/"));
+            match value.pointer(&pointer) {
+                Some(value) => code.push_str(&value.to_string()),
+                None => code.push_str("undefined"),
+            }
+        } else {
+            // not a placeholder, like the body of a function
+            code.push_str(&rest[start..=start + end]);
+        }
+        rest = &rest[start + end + 1..];
+    }
+    code.push_str(rest);
+    code
+}
+
+/// The converters for custom joi types, from `Joi.extend()`, and custom rules
+///
+/// A type converter replaces the base schema, the flags and rules of the schema are still
+/// converted. A rule converter is chained onto the schema like `email()` and takes priority
+/// over the built in rules of the same name
+#[derive(Debug, Clone)]
+pub struct Extensions {
+    types: BTreeMap<String, Extension>,
+    rules: BTreeMap<String, Extension>,
+}
+
+/// The extensions a converter can be configured with, like `{ "types": { "nullableString":
+/// "z.string().nullable()" }, "rules": { "slug": "regex(/^[a-z-]+$/)" } }`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExtensionConfig {
+    /// Template per custom type name
+    #[serde(default)]
+    pub types: BTreeMap<String, String>,
+    /// Template per custom rule name
+    #[serde(default)]
+    pub rules: BTreeMap<String, String>,
+}
+
+impl Default for Extensions {
+    /// The extensions converted out of the box
+    fn default() -> Self {
+        let mut extensions = Extensions::empty();
+        extensions.register_type("nullableString", Extension::handler(nullable_string));
+        extensions
+    }
+}
+
+impl Extensions {
+    /// No extensions, not even the ones converted out of the box
+    pub fn empty() -> Self {
+        Extensions {
+            types: BTreeMap::new(),
+            rules: BTreeMap::new(),
+        }
+    }
+
+    /// Convert the custom type `name` with `extension`, replacing any converter for it
+    pub fn register_type(&mut self, name: impl Into<String>, extension: Extension) -> &mut Self {
+        self.types.insert(name.into(), extension);
+        self
+    }
+
+    /// Convert the custom rule `name` with `extension`, replacing any converter for it
+    pub fn register_rule(&mut self, name: impl Into<String>, extension: Extension) -> &mut Self {
+        self.rules.insert(name.into(), extension);
+        self
+    }
+
+    /// Register the templates of a config
+    pub fn register_config(&mut self, config: ExtensionConfig) -> &mut Self {
+        for (name, template) in config.types {
+            self.register_type(name, Extension::Template(template));
+        }
+        for (name, template) in config.rules {
+            self.register_rule(name, Extension::Template(template));
+        }
+        self
+    }
+
+    /// If the custom type `name` has a converter
+    pub(crate) fn has_type(&self, name: &str) -> bool {
+        self.types.contains_key(name)
+    }
+
+    /// The base schema of a custom type, from the described schema
+    pub(crate) fn type_schema(&self, name: &str, describe: &Value) -> Option<js::Tokens> {
+        let extension = self.types.get(name)?;
+        Some(extension.to_tokens(describe))
+    }
+
+    /// The method for a custom rule, from the rule arguments
+    pub(crate) fn rule_method(&self, name: &str, args: Option<&Value>) -> Option<js::Tokens> {
+        let extension = self.rules.get(name)?;
+        Some(extension.to_tokens(args.unwrap_or(&Value::Null)))
+    }
+}
+
+/// A string where `""` is read as `null`
+fn nullable_string(describe: &Value) -> js::Tokens {
+    let allow: &[Value] = match describe.get("allow").and_then(Value::as_array) {
+        Some(allow) => allow,
+        None => &[],
+    };
+    let has_null = allow.iter().any(Value::is_null);
+    let empty_str = allow.iter().any(|value| value.as_str() == Some(""));
+    let strings: Vec<&str> = allow
+        .iter()
+        .filter_map(Value::as_str)
+        .filter(|value| !value.is_empty())
+        .collect();
+
+    let schema = match strings.as_slice() {
+        [] => quote! { z.string() },
+        [literal] => quote! { z.literal($[str]($[const](*literal))) },
+        strings => quote! { z.enum([$(for elem in strings join (, )=> $[str]($[const](*elem)))]) },
+    };
+    let schema = match has_null {
+        true => quote! { $schema.nullable() },
+        false => schema,
+    };
+    match empty_str {
+        true => quote! {
+            z.preprocess((val) => {
+                if (val === "") {
+                    return null;
+                }
+                return val;
+            }, $schema)
+        },
+        false => schema,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use genco::prelude::*;
+
+    use super::{Extension, ExtensionConfig, Extensions};
+    use crate::{gen_with_options, GenOptions};
+
+    fn convert(describe: &str, extensions: Extensions) -> String {
+        let options = GenOptions {
+            extensions,
+            ..GenOptions::default()
+        };
+        gen_with_options(describe.to_string(), &options).unwrap()
+    }
+
+    #[test]
+    fn test_template_extensions() {
+        let config: ExtensionConfig = serde_json::from_str(
+            r#"{
+                "types": { "money": "z.number().describe({metas.0.currency})" },
+                "rules": { "precision": "multipleOf({step})", "below": "refine((val) => val < {max}, { message: \"Too much\" })" }
+            }"#,
+        )
+        .unwrap();
+        let mut extensions = Extensions::default();
+        extensions.register_config(config);
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "money",
+                    "flags": { "presence": "required" },
+                    "metas": [{ "currency": "EUR" }],
+                    "rules": [{ "name": "precision", "args": { "step": 0.01 } }, { "name": "below", "args": { "max": 1000 } }]
+                }"#,
+                extensions
+            ),
+            r#"z.number().describe("EUR").multipleOf(0.01).refine((val) => val < 1000, { message: "Too much" })"#
+        );
+    }
+
+    #[test]
+    fn test_handler_extensions() {
+        let mut extensions = Extensions::empty();
+        extensions
+            .register_type(
+                "nullableString",
+                Extension::handler(|_| quote! { z.string().nullish() }),
+            )
+            .register_rule(
+                "min",
+                Extension::handler(|args| {
+                    let limit = args["limit"].to_string();
+                    quote! { gte($limit) }
+                }),
+            );
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "nullableString",
+                    "rules": [{ "name": "min", "args": { "limit": 2 } }]
+                }"#,
+                extensions
+            ),
+            "z.string().nullish().gte(2).optional()"
+        );
+    }
+}
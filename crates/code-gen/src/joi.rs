@@ -3,13 +3,23 @@ use genco::prelude::*;
 use serde::{self, Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::joi_types::JoiDescribeType;
+use crate::conditions;
+use crate::diagnostics::{self, Conversion};
+use crate::joi_types::{AltSchema, JoiDescribeType, JoiWhen};
+use crate::links;
+use crate::refs;
+use crate::rules::{self, RuleTokens};
+use crate::{CodeGenError, GenOptions};
 
 // https://github.com/hapijs/joi/blob/7ead57a9f8180895e110f010b425ae411451bd08/lib/index.d.ts#L1316
 // https://github.com/mrjono1/joi-to-typescript/blob/613e42022fb9847ab4c718410dbd980a457503ad/src/joiDescribeTypes.ts#LL10C56-L10C56
 
 pub trait Tokenizer {
-    fn to_tokens(&self, default_presence: bool) -> js::Tokens;
+    fn to_tokens(
+        &self,
+        default_presence: bool,
+        options: &Conversion,
+    ) -> Result<js::Tokens, CodeGenError>;
 }
 
 /// Representation of the `.describe()` response on a joi object
@@ -18,55 +28,425 @@ pub trait Tokenizer {
 pub struct JoiDescribe {
     /// The joi type specfic fields
     #[serde(flatten)]
-    type_options: JoiDescribeType,
+    pub type_options: JoiDescribeType,
     /// Flags on the schema
     #[serde(default)]
-    flags: JoiFlag,
+    pub flags: JoiFlag,
+    /// Values allowed on top of the schema, or the only values allowed when `flags.only` is set
+    #[serde(default)]
+    pub allow: Vec<serde_json::Value>,
+    /// Values the schema does not allow, from `invalid()`
+    #[serde(default)]
+    pub invalid: Vec<serde_json::Value>,
     /// Modifiers on the schema
     #[serde(default)]
-    rules: Vec<JoiRule>,
+    pub rules: Vec<JoiRule>,
+    /// Validation preferences set on the schema (like `.strict()`)
+    #[serde(default)]
+    pub preferences: JoiPreferences,
     /// Conditional schema info
-    whens: Option<serde_json::Value>,
+    pub whens: Option<Vec<JoiWhen>>,
+    /// Schemas with an `id()` that links in this schema can point to
+    #[serde(default)]
+    pub shared: Vec<JoiDescribe>,
     /// extra meta info, not used in conversion yet
     #[serde(default)]
-    metas: Vec<HashMap<String, serde_json::Value>>,
+    pub metas: Vec<HashMap<String, serde_json::Value>>,
+    /// JSON path of the schema in the describe output, like `$.keys.name`, set by `set_paths()`
+    #[serde(skip)]
+    pub path: String,
 }
 
 impl JoiDescribe {
-    pub fn convert(&self) -> genco::fmt::Result<String> {
-        self.to_tokens(true).to_string()
+    #[cfg(test)]
+    pub fn convert(&self) -> Result<String, CodeGenError> {
+        self.convert_with_options(&GenOptions::default())
+    }
+
+    #[cfg(test)]
+    pub fn convert_with_options(&self, options: &GenOptions) -> Result<String, CodeGenError> {
+        Ok(self.convert_with_diagnostics(options)?.0)
+    }
+
+    /// Convert the schema, with what of it is left out
+    pub fn convert_with_diagnostics(
+        &self,
+        options: &GenOptions,
+    ) -> Result<(String, Vec<diagnostics::Diagnostic>), CodeGenError> {
+        let mut schema = self.clone();
+        schema.set_paths("$");
+        let conversion = Conversion::new(options, &[&schema]);
+        let code = links::with_declarations(&schema, &conversion)?.to_string()?;
+        Ok((code, conversion.into_diagnostics()))
+    }
+
+    /// Set the `path` of the schema and the schemas nested in it, `path` is the JSON path of the
+    /// schema in the describe output
+    pub fn set_paths(&mut self, path: &str) {
+        let segments: Vec<String> = self
+            .children()
+            .into_iter()
+            .map(|(segment, _)| segment)
+            .collect();
+        for (segment, child) in segments.iter().zip(self.children_mut()) {
+            child.set_paths(&format!("{}{}", path, segment));
+        }
+        self.path = path.to_string();
+    }
+
+    /// Check for values that can't be converted to zod, before converting. `path` is the JSON
+    /// path of the schema in the describe output, like `$`
+    pub fn validate(&self, path: &str) -> Result<(), CodeGenError> {
+        for (index, value) in self.allow.iter().enumerate() {
+            let literal = !value.is_object() && !value.is_array();
+            if !literal && !is_override(value) && !refs::is_ref(value) {
+                return Err(CodeGenError::UnsupportedAllowValue {
+                    path: format!("{}.allow[{}]", path, index),
+                    value: value.clone(),
+                });
+            }
+        }
+        for (segment, child) in self.children() {
+            child.validate(&format!("{}{}", path, segment))?;
+        }
+        Ok(())
+    }
+
+    /// The schemas nested in this one, with the JSON path segment leading to each
+    pub fn children(&self) -> Vec<(String, &JoiDescribe)> {
+        let mut children: Vec<(String, &JoiDescribe)> = Vec::new();
+        match &self.type_options {
+            JoiDescribeType::Object(object) => {
+                for (key, child) in object.keys.iter() {
+                    children.push((format!(".keys{}", key_segment(key)), child));
+                }
+                for (index, pattern) in object.patterns.iter().enumerate() {
+                    if let Some(schema) = &pattern.schema {
+                        children.push((format!(".patterns[{}].schema", index), schema));
+                    }
+                    children.push((format!(".patterns[{}].rule", index), &pattern.rule));
+                }
+            }
+            JoiDescribeType::Array(array) => {
+                for (index, item) in array.items.iter().enumerate() {
+                    children.push((format!(".items[{}]", index), item));
+                }
+                for (index, item) in array.ordered.iter().enumerate() {
+                    children.push((format!(".ordered[{}]", index), item));
+                }
+            }
+            JoiDescribeType::Alternatives(alt) => {
+                for (index, one_match) in alt.matches.iter().enumerate() {
+                    let path = format!(".matches[{}]", index);
+                    match one_match {
+                        AltSchema::Schema { schema } => {
+                            children.push((format!("{}.schema", path), schema))
+                        }
+                        AltSchema::Conditional(when) => when_children(&path, when, &mut children),
+                    }
+                }
+            }
+            _ => {}
+        }
+        for (index, when) in self.whens.iter().flatten().enumerate() {
+            when_children(&format!(".whens[{}]", index), when, &mut children);
+        }
+        for (index, shared) in self.shared.iter().enumerate() {
+            children.push((format!(".shared[{}]", index), shared));
+        }
+        children
+    }
+
+    /// The schemas nested in this one, in the same order as `children()`
+    pub fn children_mut(&mut self) -> Vec<&mut JoiDescribe> {
+        let mut children: Vec<&mut JoiDescribe> = Vec::new();
+        match &mut self.type_options {
+            JoiDescribeType::Object(object) => {
+                children.extend(object.keys.values_mut());
+                for pattern in object.patterns.iter_mut() {
+                    children.extend(pattern.schema.as_mut());
+                    children.push(&mut pattern.rule);
+                }
+            }
+            JoiDescribeType::Array(array) => {
+                children.extend(array.items.iter_mut());
+                children.extend(array.ordered.iter_mut());
+            }
+            JoiDescribeType::Alternatives(alt) => {
+                for one_match in alt.matches.iter_mut() {
+                    match one_match {
+                        AltSchema::Schema { schema } => children.push(schema),
+                        AltSchema::Conditional(when) => when_children_mut(when, &mut children),
+                    }
+                }
+            }
+            _ => {}
+        }
+        for when in self.whens.iter_mut().flatten() {
+            when_children_mut(when, &mut children);
+        }
+        children.extend(self.shared.iter_mut());
+        children
+    }
+
+    /// Add the zod for the rule at `index` of this schema to `out`, false if the rule isn't
+    /// supported
+    pub fn rule_to_tokens(
+        &self,
+        index: usize,
+        options: &Conversion,
+        out: &mut RuleTokens,
+    ) -> Result<bool, CodeGenError> {
+        let rule = &self.rules[index];
+        let name = rule.name.as_str();
+        let args = rule.args.as_ref();
+        if let Some(method) = options.extensions.rule_method(name, args) {
+            out.methods.push(method);
+            return Ok(true);
+        }
+        let rule_path = format!("{}.rules[{}]", self.path, index);
+        let handled = match &self.type_options {
+            JoiDescribeType::String(_) => {
+                rules::string_rule(rule, self, out).map_err(|error| error.rebase(&rule_path))?
+            }
+            JoiDescribeType::Number(_) => {
+                rules::number_rule(rule, self, out).map_err(|error| error.rebase(&rule_path))?
+            }
+            JoiDescribeType::Date(_) => rules::date_rule(rule, self, out),
+            JoiDescribeType::Array(arr) => {
+                rules::array_rule(rule, &rule_path, arr, self, options, out)?
+            }
+            JoiDescribeType::Object(_) => rules::object_rule(rule, self, out),
+            JoiDescribeType::Binary(_) => rules::binary_rule(rule, self, out),
+            JoiDescribeType::Function(_) => rules::function_rule(rule, self, out),
+            _ => false,
+        };
+        if handled {
+            return Ok(true);
+        }
+        match name {
+            "integer" => out.methods.push(quote! {int()}),
+            "min" | "max" | "length" => {
+                let path = format!("{}.args", rule_path);
+                // dates are limited by a `date` instead
+                let arg = match &self.type_options {
+                    JoiDescribeType::Date(_) => "date",
+                    _ => "limit",
+                };
+                let limit = match args {
+                    None => None,
+                    Some(serde_json::Value::Object(args)) => args.get(arg),
+                    Some(_) => return Err(CodeGenError::MalformedDescribe { path }),
+                };
+                let Some(limit) = limit.filter(|limit| !limit.is_null()) else {
+                    return Err(CodeGenError::MissingRuleArg {
+                        rule: name.to_string(),
+                        path: format!("{}.{}", path, arg),
+                    });
+                };
+                let val = format!("{}", limit);
+                out.methods.push(quote! {$name($val)});
+            }
+            "unique" => out.refines.push(quote! {
+                (arr) => {
+                    return !arr || (new Set(arr)).size === arr.length;
+                }, {message: "Array most not have duplicate values"}
+            }),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Merge another schema into this one like joi's `concat()`, which is how the `then` and
+    /// `otherwise` of a condition are applied
+    pub fn concat(&self, other: &JoiDescribe) -> JoiDescribe {
+        let type_options = match &other.type_options {
+            // `Joi.required()` and friends only change the flags
+            JoiDescribeType::Any(_) => self.type_options.clone(),
+            type_options => type_options.clone(),
+        };
+        JoiDescribe {
+            type_options,
+            flags: self.flags.concat(&other.flags),
+            allow: match other.allow.iter().any(is_override) {
+                true => other.allow.clone(),
+                false => self
+                    .allow
+                    .iter()
+                    .chain(
+                        other
+                            .allow
+                            .iter()
+                            .filter(|value| !self.allow.contains(value)),
+                    )
+                    .cloned()
+                    .collect(),
+            },
+            // values allowed again by the other schema are no longer invalid
+            invalid: self
+                .invalid
+                .iter()
+                .filter(|value| !other.allow.contains(value))
+                .chain(
+                    other
+                        .invalid
+                        .iter()
+                        .filter(|value| !self.invalid.contains(value)),
+                )
+                .cloned()
+                .collect(),
+            rules: self
+                .rules
+                .iter()
+                .chain(other.rules.iter())
+                .cloned()
+                .collect(),
+            preferences: JoiPreferences {
+                convert: other.preferences.convert.or(self.preferences.convert),
+            },
+            whens: match (&self.whens, &other.whens) {
+                (None, None) => None,
+                (whens, other_whens) => Some(
+                    whens
+                        .iter()
+                        .chain(other_whens.iter())
+                        .flatten()
+                        .cloned()
+                        .collect(),
+                ),
+            },
+            shared: self
+                .shared
+                .iter()
+                .chain(other.shared.iter())
+                .cloned()
+                .collect(),
+            metas: self
+                .metas
+                .iter()
+                .chain(other.metas.iter())
+                .cloned()
+                .collect(), // the conditions of a key are applied to the key
+            path: self.path.clone(),
+        }
+    }
+}
+
+/// The schemas of a condition, with the JSON path segment leading to each
+fn when_children<'a>(path: &str, when: &'a JoiWhen, children: &mut Vec<(String, &'a JoiDescribe)>) {
+    let schemas = [
+        ("is", &when.is),
+        ("not", &when.not),
+        ("then", &when.then),
+        ("otherwise", &when.otherwise),
+    ];
+    for (name, schema) in schemas {
+        if let Some(schema) = schema {
+            children.push((format!("{}.{}", path, name), schema));
+        }
+    }
+    for (index, case) in when.switch.iter().flatten().enumerate() {
+        children.push((format!("{}.switch[{}].is", path, index), &case.is));
+        let schemas = [("then", &case.then), ("otherwise", &case.otherwise)];
+        for (name, schema) in schemas {
+            if let Some(schema) = schema {
+                children.push((format!("{}.switch[{}].{}", path, index, name), schema));
+            }
+        }
+    }
+}
+
+fn when_children_mut<'a>(when: &'a mut JoiWhen, children: &mut Vec<&'a mut JoiDescribe>) {
+    let schemas = [
+        &mut when.is,
+        &mut when.not,
+        &mut when.then,
+        &mut when.otherwise,
+    ];
+    children.extend(schemas.into_iter().flatten());
+    for case in when.switch.iter_mut().flatten() {
+        children.push(&mut case.is);
+        children.extend(case.then.as_mut());
+        children.extend(case.otherwise.as_mut());
     }
 }
 
+/// The JSON path segment for an object key
+pub fn key_segment(key: &str) -> String {
+    let identifier = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && !key.starts_with(|c: char| c.is_ascii_digit());
+    match identifier {
+        true => format!(".{}", key),
+        false => format!("[{}]", serde_json::Value::from(key)),
+    }
+}
+
+/// If an allow list value is the `Joi.override` marker, which replaces the values of a schema it
+/// is concatenated to instead of adding to them
+pub fn is_override(value: &serde_json::Value) -> bool {
+    value.get("override") == Some(&serde_json::Value::Bool(true))
+}
+
 /// Joi refinement rules
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct JoiRule {
     /// The rule
-    name: String,
+    pub name: String,
     /// Optional args for the rule (like min or max value)
-    args: Option<serde_json::Value>,
+    pub args: Option<serde_json::Value>,
+}
+
+/// Joi validation preferences
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiPreferences {
+    /// If joi should convert values before validating (false when `.strict()` is used)
+    pub convert: Option<bool>,
+}
+
+impl JoiPreferences {
+    /// joi converts values unless told otherwise
+    pub fn convert(&self) -> bool {
+        self.convert.unwrap_or(true)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct JoiFlag {
     /// required | optional | forbidden
-    presence: Option<String>,
-    description: Option<String>,
-    label: Option<String>,
+    pub presence: Option<String>,
+    pub description: Option<String>,
+    pub label: Option<String>,
     /// "strip" | ?
-    result: Option<String>,
+    pub result: Option<String>,
 
     /// Default value on parse error
-    default: Option<serde_json::Value>,
+    pub default: Option<serde_json::Value>,
     /// If should only allow values in the allow list
     #[serde(default)]
-    only: bool, // default to false
+    pub only: bool, // default to false
     /// If an array can parse a single element not in an array
     #[serde(default)]
-    single: bool, // default to false
+    pub single: bool, // default to false
+    /// The string format a date is parsed from: iso | javascript | unix
+    pub format: Option<String>,
+    /// If an array can have `undefined` elements
+    pub sparse: Option<bool>,
+    /// If an object allows keys that are not listed
+    pub unknown: Option<bool>,
+    /// How many alternatives have to match: any | one | all
+    pub r#match: Option<String>,
+    /// The encoding binary schemas convert strings from
+    pub encoding: Option<String>,
+    /// The name of the schema for `Joi.link()`
+    pub id: Option<String>,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for JoiFlag {
     fn default() -> Self {
         Self {
@@ -77,12 +457,47 @@ impl Default for JoiFlag {
             result: None,
             only: false,
             single: false,
+            format: None,
+            sparse: None,
+            unknown: None,
+            r#match: None,
+            encoding: None,
+            id: None,
+        }
+    }
+}
+
+impl JoiFlag {
+    /// Merge the flags of another schema into these, the other schema's flags win
+    pub fn concat(&self, other: &JoiFlag) -> JoiFlag {
+        JoiFlag {
+            presence: other.presence.clone().or_else(|| self.presence.clone()),
+            description: other
+                .description
+                .clone()
+                .or_else(|| self.description.clone()),
+            label: other.label.clone().or_else(|| self.label.clone()),
+            result: other.result.clone().or_else(|| self.result.clone()),
+            default: other.default.clone().or_else(|| self.default.clone()),
+            only: self.only || other.only,
+            single: self.single || other.single,
+            format: other.format.clone().or_else(|| self.format.clone()),
+            sparse: other.sparse.or(self.sparse),
+            unknown: other.unknown.or(self.unknown),
+            r#match: other.r#match.clone().or_else(|| self.r#match.clone()),
+            encoding: other.encoding.clone().or_else(|| self.encoding.clone()),
+            // the merged schema is a new schema, it doesn't keep the name of the first
+            id: other.id.clone(),
         }
     }
 }
 
 impl Tokenizer for JoiFlag {
-    fn to_tokens(&self, default_optional: bool) -> js::Tokens {
+    fn to_tokens(
+        &self,
+        default_optional: bool,
+        _options: &Conversion,
+    ) -> Result<js::Tokens, CodeGenError> {
         let description: Option<js::Tokens> = self.description.as_ref().map(|desc| {
             quote! {
                 describe($[str]($[const](desc)))
@@ -95,11 +510,6 @@ impl Tokenizer for JoiFlag {
             }
         });
 
-        let strip_msg: Option<js::Tokens> = match self.result.as_ref().map(|r| r.as_str()) {
-            Some("strip") => Some(quote! {__please_handle_strip__()}),
-            _ => None,
-        };
-
         let default: Option<js::Tokens> = self.default.as_ref().map(|def| {
             let def = format!("{}", def);
             quote! {
@@ -107,25 +517,19 @@ impl Tokenizer for JoiFlag {
             }
         });
 
-        let presence = self
-            .presence
-            .as_ref()
-            .map(|pres| {
-                let str = match pres.as_str() {
-                    "optional" => quote! { optional() },
-                    "required" => quote! { required() },
-                    "forbidden" => quote! { undefined() },
-                    _ => unreachable!(),
-                };
-                str
-            })
-            .unwrap_or_else(|| {
-                if default_optional {
-                    quote! {optional()}
-                } else {
-                    quote! {required()}
-                }
-            });
+        let presence = match self.presence.as_deref() {
+            Some("optional") => quote! { optional() },
+            Some("required") => quote! { required() },
+            Some("forbidden") => quote! { undefined() },
+            Some(presence) => {
+                return Err(CodeGenError::UnsupportedPresence {
+                    path: "$.flags.presence".to_string(),
+                    presence: presence.to_string(),
+                })
+            }
+            None if default_optional => quote! {optional()},
+            None => quote! {required()},
+        };
 
         let mut flag_tokens = Vec::new();
 
@@ -157,13 +561,9 @@ impl Tokenizer for JoiFlag {
             flag_tokens.push(label);
         }
 
-        if let Some(strip_msg) = strip_msg {
-            flag_tokens.push(strip_msg);
-        }
-
-        quote! {
+        Ok(quote! {
             $(for flag in flag_tokens.iter() join (.)=> $flag)
-        }
+        })
     }
 }
 
@@ -179,46 +579,45 @@ fn join_tokens_with_dot(start: js::Tokens, extra: js::Tokens) -> js::Tokens {
 }
 
 impl Tokenizer for JoiDescribe {
-    fn to_tokens(&self, default_optional: bool) -> js::Tokens {
-        // a pre process function to apply to the schema
-        // https://zod.dev/?id=preprocess
-        let mut pre_process: Option<js::Tokens> = None;
-        // a refine function to apply to the schema
-        // https://zod.dev/?id=refine
-        // TODO: maybe make this a list and make a super refine func?
-        let mut refine: Option<js::Tokens> = None;
-
-        let mut handle_string_allow = |allow: &Vec<serde_json::Value>| -> js::Tokens {
-            let mut has_null = false;
-            let mut empty_str = false;
-            let mut non_empty = Vec::with_capacity(allow.len());
-
-            for value in allow.iter() {
-                if value.is_null() {
-                    has_null = true;
-                } else if value.as_str().map(|s| s.is_empty()).unwrap_or(false) {
-                    empty_str = true;
-                } else {
-                    non_empty.push(
-                        value
-                            .as_str()
-                            .expect("Passed non string value to allow list of string schema"),
-                    )
-                }
-            }
+    fn to_tokens(
+        &self,
+        default_optional: bool,
+        options: &Conversion,
+    ) -> Result<js::Tokens, CodeGenError> {
+        // schemas with an id are declared on their own, see `links::with_declarations`
+        if let Some(id) = &self.flags.id {
+            return links::declared_schema(id, self, default_optional, options);
+        }
 
-            let mut non_nulls = non_empty.iter().map(|elem| format!("{}", elem));
-            let zod_schema = if non_nulls.len() > 1 {
-                quote! { z.enum([$(for elem in non_nulls join (, )=> $[str]($[const](elem)))]) }
-            } else if let Some(literal) = non_nulls.next() {
-                quote! { z.literal($[str]($[const](literal))) }
-            } else {
-                quote! { z.string() }
+        // the pre processes, methods and refine functions to apply to the schema
+        let mut rule_tokens = RuleTokens::default();
+
+        let mut handle_string_allow = |allow: &[serde_json::Value]| -> js::Tokens {
+            let allow = refs::allow_literals(allow);
+            let has_null = allow.iter().any(|value| value.is_null());
+            let empty_str = allow.iter().any(|value| value.as_str() == Some(""));
+            let non_empty: Vec<&serde_json::Value> = allow
+                .iter()
+                .filter(|value| !value.is_null() && value.as_str() != Some(""))
+                .collect();
+            let strings: Option<Vec<&str>> = non_empty.iter().map(|value| value.as_str()).collect();
+
+            let zod_schema = match strings.as_deref() {
+                Some([]) => quote! { z.string() },
+                Some([literal]) => quote! { z.literal($[str]($[const](*literal))) },
+                Some(strings) => {
+                    quote! { z.enum([$(for elem in strings join (, )=> $[str]($[const](*elem)))]) }
+                }
+                // joi allows valid values of any type on a string schema
+                None => {
+                    let values: Vec<serde_json::Value> = non_empty.into_iter().cloned().collect();
+                    rules::literal_schema(&values).unwrap_or_else(|| quote! { z.string() })
+                }
             };
 
             // turn empty str into null
             if empty_str {
-                pre_process = Some(quote! {
+                rule_tokens.pre_processes.push(quote! {
                     (val) => {
                         if (val === "") {
                             return null;
@@ -236,21 +635,77 @@ impl Tokenizer for JoiDescribe {
             }
         };
 
+        // the parent object checks allow lists with references
+        let valid_only = self.flags.only && refs::allow_refs(self).is_empty();
+
         let value: js::Tokens = match &self.type_options {
+            // a class without a name is reported by `Conversion::record`
+            JoiDescribeType::Object(_) if rules::is_instance(self) => {
+                match rules::instance_of(self) {
+                    Some(class) => quote! { z.instanceof($class) },
+                    None => quote! { z.any() },
+                }
+            }
             JoiDescribeType::Object(object) => {
-                let result = object
-                    .keys
+                let patterns: Vec<(js::Tokens, js::Tokens)> = object
+                    .patterns
                     .iter()
-                    .map(|(key, value)| (key, value.to_tokens(true)));
-                quote! {
-                    z.object({
-                        $(for (key, value) in result join (,$['\r'])=> $key: $value)
+                    .map(|pattern| {
+                        let key = match (&pattern.regex, &pattern.schema) {
+                            (Some(regex), _) => {
+                                let regex = rules::regex_literal(regex);
+                                quote! { z.string().regex($regex) }
+                            }
+                            (None, Some(schema)) => schema.to_tokens(false, options)?,
+                            (None, None) => quote! { z.string() },
+                        };
+                        Ok((key, pattern.rule.to_tokens(false, options)?))
                     })
+                    .collect::<Result<_, CodeGenError>>()?;
+
+                rules::object_dependencies(object, &mut rule_tokens);
+                refs::object_refs(object, &mut rule_tokens);
+                match patterns.as_slice() {
+                    // a pure dictionary
+                    [(key, value)]
+                        if object.keys.is_empty() && self.flags.unknown != Some(true) =>
+                    {
+                        quote! { z.record($key, $value) }
+                    }
+                    _ => {
+                        let catchall = match patterns.as_slice() {
+                            [] => None,
+                            [(_, value)] => Some(value.clone()),
+                            _ => {
+                                let values = patterns.iter().map(|(_, value)| value);
+                                Some(
+                                    quote! { z.union([$(for value in values join (, )=> $value)]) },
+                                )
+                            }
+                        };
+                        if catchall.is_some() {
+                            rules::object_pattern_keys(object, &patterns, self, &mut rule_tokens);
+                        }
+                        let make_object = |keys: Vec<(&String, js::Tokens)>| -> js::Tokens {
+                            let schema = quote! {
+                                z.object({
+                                    $(for (key, value) in keys join (,$['\r'])=> $key: $value)
+                                })
+                            };
+                            match (&catchall, self.flags.unknown) {
+                                (Some(value), _) => quote! { $schema.catchall($value) },
+                                (None, Some(true)) => quote! { $schema.passthrough() },
+                                (None, Some(false)) => quote! { $schema.strict() },
+                                (None, None) => schema,
+                            }
+                        };
+                        conditions::object_schema(object, options, make_object, &mut rule_tokens)?
+                    }
                 }
             }
             JoiDescribeType::Array(arr) => {
                 if self.flags.single {
-                    pre_process = Some(quote! {
+                    rule_tokens.pre_processes.push(quote! {
                         (val) => {
                             if (val === undefined || val === null) {
                                 return val;
@@ -262,106 +717,183 @@ impl Tokenizer for JoiDescribe {
                         }
                     })
                 }
-                let mut children = arr.items.iter().map(|child| child.to_tokens(false));
+                let children = arr
+                    .items
+                    .iter()
+                    .map(|child| child.to_tokens(false, options))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut children = children.into_iter();
                 let element = if children.len() > 1 {
                     // not sure how common multiple array items is but i guess we wrap in union?
-                    quote! { z.union([$(for child in children join (, )=> $child)]) }
+                    Some(quote! { z.union([$(for child in children join (, )=> $child)]) })
                 } else {
-                    children.next().unwrap()
+                    children.next()
+                };
+                // sparse arrays can have holes in them
+                let element = match element {
+                    Some(element) if self.flags.sparse == Some(true) => {
+                        Some(quote! { $element.optional() })
+                    }
+                    element => element,
                 };
-                quote! { z.array($element) }
+
+                if arr.ordered.is_empty() {
+                    // `Joi.array()` without items allows anything
+                    let element = element.unwrap_or_else(|| {
+                        if options.unknown_array_items {
+                            quote! { z.unknown() }
+                        } else {
+                            quote! { z.any() }
+                        }
+                    });
+                    quote! { z.array($element) }
+                } else {
+                    // ordered items are optional unless required, like object keys
+                    let ordered = arr
+                        .ordered
+                        .iter()
+                        .map(|child| child.to_tokens(true, options))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let tuple = |ordered: &[js::Tokens]| -> js::Tokens {
+                        quote! { z.tuple([$(for child in ordered join (, )=> $child)]) }
+                    };
+                    // any items after the ordered ones have to match `items`
+                    let full = match element {
+                        Some(element) => quote! { $(tuple(&ordered)).rest($element) },
+                        None => tuple(&ordered),
+                    };
+                    // zod needs every position of a tuple so each shorter length joi accepts is
+                    // a tuple of its own
+                    let min_length = rules::ordered_min_length(arr);
+                    match min_length < ordered.len() {
+                        true => {
+                            let shorter =
+                                (min_length..ordered.len()).map(|len| tuple(&ordered[..len]));
+                            let tuples = shorter.chain(std::iter::once(full));
+                            quote! { z.union([$(for tuple in tuples join (, )=> $tuple)]) }
+                        }
+                        false => full,
+                    }
+                }
             }
-            JoiDescribeType::Alternatives(alt) => quote! {
-                z.union([$(for one_match in alt.matches.iter() join (, )=> $(one_match.schema.to_tokens(false)))])
-            },
-            JoiDescribeType::String(str) => {
-                if !self.flags.only {
+            JoiDescribeType::Alternatives(alt) => {
+                conditions::alternatives_schema(alt, self, options, &mut rule_tokens)?
+            }
+            JoiDescribeType::String(_) => {
+                if !valid_only {
                     quote! { z.string() }
                 } else {
-                    handle_string_allow(&str.allow)
+                    handle_string_allow(&self.allow)
                 }
             }
-            JoiDescribeType::Date(_) => quote! { z.date() },
-            JoiDescribeType::Number(number) => {
-                let allow = &number.allow;
-                if !self.flags.only {
-                    quote! { z.number() }
-                } else {
-                    if allow.is_empty() {
-                        unreachable!();
-                    }
-                    let elems = allow.iter().map(|elem| format!("{}", elem));
-                    quote! { z.union([$(for elem in elems join (, )=> z.literal($elem))]) }
+            JoiDescribeType::Date(_) => {
+                if valid_only {
+                    rules::valid_dates(self, &mut rule_tokens);
+                }
+                rules::date_schema(self, &mut rule_tokens)
+            }
+            JoiDescribeType::Number(_) => match rules::literal_schema(&self.allow) {
+                Some(literals) if valid_only => literals,
+                _ => quote! { z.number() },
+            },
+            JoiDescribeType::Boolean(_) if valid_only => {
+                rules::literal_schema(&self.allow).unwrap_or_else(|| quote! { z.boolean() })
+            }
+            JoiDescribeType::Boolean(_) => {
+                let invalid = &self.invalid;
+                match (
+                    invalid.contains(&true.into()),
+                    invalid.contains(&false.into()),
+                ) {
+                    (true, true) => quote! { z.never() },
+                    (true, false) => quote! { z.literal(false) },
+                    (false, true) => quote! { z.literal(true) },
+                    (false, false) => quote! { z.boolean() },
                 }
             }
-            JoiDescribeType::Boolean(_) => quote! { z.boolean() },
 
-            JoiDescribeType::Any(_) => quote! { z.any() },
+            JoiDescribeType::Any(_) => match rules::literal_schema(&self.allow) {
+                Some(literals) if valid_only => literals,
+                _ => quote! { z.any() },
+            },
+            JoiDescribeType::Binary(_) => rules::binary_schema(self, &mut rule_tokens),
+            JoiDescribeType::Symbol(symbol) => rules::symbol_schema(symbol, self),
+            JoiDescribeType::Function(_) => rules::function_schema(self),
+            JoiDescribeType::Link(link) => links::link_schema(link),
             JoiDescribeType::Unknown(joi_unknown) => {
                 let ty = &joi_unknown.joi_type;
-                match ty.as_str() {
-                    "nullableString" => {
-                        // TODO: handle this gracefully but for now assuming the structure is like a string
-                        let allows = joi_unknown
-                            .unknown_fields
-                            .get("allow")
-                            .unwrap()
-                            .as_array()
-                            .unwrap();
-                        handle_string_allow(allows)
-                    }
-                    _ => quote! { z.$ty.__please_fix_me__() },
+                let describe = serde_json::to_value(self).unwrap_or_default();
+                match options.extensions.type_schema(ty, &describe) {
+                    Some(schema) => schema,
+                    None => quote! { z.any() },
                 }
             }
         };
 
-        let mut extra_flags: Vec<js::Tokens> = Vec::new();
-        for rule in self.rules.iter() {
-            let name = rule.name.as_str();
-            let args = rule.args.as_ref();
-            match name {
-                "integer" => extra_flags.push(quote! {int()}),
-                "min" | "max" => {
-                    let val = args
-                        .unwrap()
-                        .pointer("/limit")
-                        .expect("min|max should have limit");
-                    let val = format!("{}", val);
-                    extra_flags.push(quote! {$name($val)});
-                }
-                "unique" => {
-                    refine = Some(quote! {
-                        (arr) => {
-                            return !arr || (new Set(arr)).size === arr.length;
-                        }, {message: "Array most not have duplicate values"}
-                    })
-                }
-                _ => {
-                    let args = args.map(|a| format!("{}", a)).unwrap_or_default();
-                    extra_flags.push(quote! {$name.__please_fix_me__($args)});
-                }
+        let mut rule_diagnostics = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            // rules with a reference are checked by the object the reference points into
+            let supported = match refs::rule_ref(rule) {
+                Some(_) => refs::is_checkable(self, rule),
+                None => self.rule_to_tokens(index, options, &mut rule_tokens)?,
+            };
+            if !supported {
+                rule_diagnostics.push(diagnostics::unsupported_rule(&rule.name));
             }
         }
 
-        let extra_flag_tokens = quote! {$(for elem in extra_flags join (.)=> $elem)};
-        let schema = join_tokens_with_dot(value, extra_flag_tokens);
+        rule_diagnostics.append(&mut rule_tokens.diagnostics);
+        if let JoiDescribeType::Array(_) = &self.type_options {
+            rules::array_sparse(self, &mut rule_tokens);
+        }
+        rules::invalid_values(self, &mut rule_tokens);
 
-        let flag_tokens = self.flags.to_tokens(default_optional);
-        let schema = join_tokens_with_dot(schema, flag_tokens);
+        let extra_flag_tokens = quote! {
+            $(for elem in rule_tokens.transforms.iter().chain(rule_tokens.methods.iter()) join (.)=> $elem)
+        };
+        let schema = join_tokens_with_dot(value, extra_flag_tokens);
 
-        let schema = match refine {
-            Some(refine_fn) => quote! {$schema.refine($refine_fn)},
-            None => schema,
+        let refine = |schema: js::Tokens| {
+            let schema = rule_tokens.refines.iter().fold(
+                schema,
+                |schema, refine_fn| quote! {$schema.refine($refine_fn)},
+            );
+            rule_tokens.super_refines.iter().fold(
+                schema,
+                |schema, refine_fn| quote! {$schema.superRefine($refine_fn)},
+            )
+        };
+        // the values of `allow()` skip the rules, so the refines go on the schema before the
+        // allowed literals are added to it
+        let refines_first = !rules::allow_extra_literals(self).is_empty();
+        let schema = match refines_first {
+            true => refine(schema),
+            false => schema,
         };
+        let schema = rules::allow_extras(self, schema);
+
+        let flag_tokens = self
+            .flags
+            .to_tokens(default_optional, options)
+            .map_err(|error| error.rebase(&self.path))?;
+        let schema = join_tokens_with_dot(schema, flag_tokens);
 
-        let schema = match self.whens {
-            Some(_) => quote! {$schema.TODO_handle_conditions()},
-            None => schema,
+        let schema = match refines_first {
+            true => schema,
+            false => refine(schema),
         };
 
-        match pre_process {
-            Some(pre) => quote!(z.preprocess($pre, $schema)),
-            None => schema,
+        // the first pre process should run first so it wraps the others
+        let schema = rule_tokens
+            .pre_processes
+            .iter()
+            .rev()
+            .fold(schema, |schema, pre| quote!(z.preprocess($pre, $schema)));
+
+        let diagnostics = options.record(self, rule_diagnostics);
+        match options.todo_comments {
+            true => Ok(diagnostics::with_todo_comments(&diagnostics, schema)),
+            false => Ok(schema),
         }
     }
 }
@@ -370,6 +902,7 @@ impl Tokenizer for JoiDescribe {
 mod tests {
 
     use super::JoiDescribe;
+    use crate::{CodeGenError, GenOptions};
 
     #[test]
     fn test_convert_simple_any() {
@@ -382,7 +915,7 @@ mod tests {
         }"#;
 
         let joi: JoiDescribe = serde_json::from_str(describe).expect("should work...");
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
 
         assert_eq!(
             tokens,
@@ -405,7 +938,7 @@ mod tests {
         }"#;
 
         let joi: JoiDescribe = serde_json::from_str(describe).expect("should work...");
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
 
         assert_eq!(
             tokens,
@@ -471,13 +1004,13 @@ mod tests {
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(
             tokens,
             Ok(r#"
 z.object({
     count: z.number(),
-    dateCreated: z.date(),
+    dateCreated: z.coerce.date(),
     int: z.number().int().optional(),
     name: z.string().optional().describe("Test Schema Name"),
     obj: z.object({}).optional(),
@@ -516,13 +1049,37 @@ z.object({
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(
             tokens,
             Ok("z.array(z.string()).optional().describe(\"A list of Test object\")".to_string())
         )
     }
 
+    #[test]
+    fn test_parse_array_without_items() {
+        let joi: JoiDescribe = serde_json::from_str(r#"{ "type": "array" }"#).unwrap();
+
+        assert_eq!(
+            joi.convert().map_err(|e| e.to_string()),
+            Ok("z.array(z.any()).optional()".to_string())
+        );
+        assert_eq!(
+            joi.convert_with_options(&GenOptions {
+                unknown_array_items: true,
+                ..GenOptions::default()
+            })
+            .map_err(|e| e.to_string()),
+            Ok("z.array(z.unknown()).optional()".to_string())
+        );
+
+        let joi: JoiDescribe = serde_json::from_str(r#"{ "type": "array", "items": [] }"#).unwrap();
+        assert_eq!(
+            joi.convert().map_err(|e| e.to_string()),
+            Ok("z.array(z.any()).optional()".to_string())
+        );
+    }
+
     #[test]
     fn test_basic_parse_array_unique() {
         let joi: JoiDescribe = serde_json::from_str(
@@ -545,7 +1102,7 @@ z.object({
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(
             tokens,
             Ok(r#"z.preprocess((val) => {
@@ -580,7 +1137,7 @@ z.object({
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(tokens, Ok("z.enum([\"foo\", \"bar\"])".to_string()))
     }
 
@@ -600,7 +1157,7 @@ z.object({
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(tokens, Ok("z.literal(\"foo\")".to_string()))
     }
 
@@ -621,13 +1178,148 @@ z.object({
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(
             tokens,
             Ok("z.union([z.literal(3), z.literal(4)])".to_string())
         )
     }
 
+    #[test]
+    fn test_convert_string_with_invalid() {
+        let joi: JoiDescribe =
+            serde_json::from_str(r#"{"type":"string","invalid":["bar","quz"]}"#).unwrap();
+
+        let tokens = joi.convert().map_err(|e| e.to_string());
+        assert_eq!(
+            tokens,
+            Ok(r#"z.string().optional().refine((val) => {
+    return val == null || !["bar", "quz"].includes(val);
+}, {message: "Contains an invalid value"})"#
+                .to_string())
+        )
+    }
+
+    #[test]
+    fn test_convert_custom_type_with_invalid() {
+        let joi: JoiDescribe =
+            serde_json::from_str(r#"{"type":"money","invalid":["free"]}"#).unwrap();
+
+        let tokens = joi.convert().map_err(|e| e.to_string());
+        assert_eq!(
+            tokens,
+            Ok(r#"z.any().optional().refine((val) => {
+    return val == null || !["free"].includes(val);
+}, {message: "Contains an invalid value"})"#
+                .to_string())
+        )
+    }
+
+    #[test]
+    fn test_convert_string_with_mixed_valid() {
+        let joi: JoiDescribe = serde_json::from_str(
+            r#"{"type":"string","flags":{"only":true},"allow":[{"override":true},"a",1,null]}"#,
+        )
+        .unwrap();
+
+        let tokens = joi.convert().map_err(|e| e.to_string());
+        assert_eq!(
+            tokens,
+            Ok(r#"z.union([z.literal("a"), z.literal(1)]).nullable().optional()"#.to_string())
+        )
+    }
+
+    #[test]
+    fn test_unsupported_allow_value() {
+        let describe = r#"{
+            "type": "object",
+            "keys": {
+                "a b": {
+                    "type": "array",
+                    "items": [{ "type": "string", "flags": { "only": true }, "allow": ["a", { "b": 1 }] }]
+                }
+            }
+        }"#;
+
+        match crate::gen(describe.to_string()) {
+            Err(CodeGenError::UnsupportedAllowValue { path, value }) => {
+                assert_eq!(path, r#"$.keys["a b"].items[0].allow[1]"#);
+                assert_eq!(value, serde_json::json!({ "b": 1 }));
+            }
+            result => panic!("expected an unsupported value error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_malformed_errors() {
+        let describe = r#"{
+            "type": "object",
+            "keys": {
+                "tags": {
+                    "type": "array",
+                    "rules": [{ "name": "has", "args": { "schema": { "type": "string", "flags": { "presence": "sometimes" } } } }]
+                }
+            }
+        }"#;
+        match crate::gen(describe.to_string()) {
+            Err(error @ CodeGenError::UnsupportedPresence { .. }) => {
+                assert_eq!(
+                    error.to_string(),
+                    r#"unsupported presence "sometimes" at $.keys.tags.rules[0].args.schema.flags.presence"#
+                );
+                assert_eq!(error.code(), "joi_to_zod::unsupported_presence");
+            }
+            result => panic!("expected an unsupported presence error, got {:?}", result),
+        }
+
+        let describe = r#"{
+            "type": "array",
+            "items": [{ "type": "string", "rules": [{ "name": "trim" }, { "name": "min", "args": {} }] }]
+        }"#;
+        match crate::gen(describe.to_string()) {
+            Err(CodeGenError::MissingRuleArg { rule, path }) => {
+                assert_eq!(rule, "min");
+                assert_eq!(path, "$.items[0].rules[1].args.limit");
+            }
+            result => panic!("expected a missing rule argument error, got {:?}", result),
+        }
+
+        let describe = r#"{ "type": "string", "rules": [{ "name": "pattern", "args": {} }] }"#;
+        match crate::gen(describe.to_string()) {
+            Err(CodeGenError::MissingRuleArg { rule, path }) => {
+                assert_eq!(rule, "pattern");
+                assert_eq!(path, "$.rules[0].args.regex");
+            }
+            result => panic!("expected a missing rule argument error, got {:?}", result),
+        }
+
+        let describe = r#"{ "type": "number", "rules": [{ "name": "min", "args": { "limit": 1 } }, { "name": "multiple" }] }"#;
+        match crate::gen(describe.to_string()) {
+            Err(CodeGenError::MissingRuleArg { rule, path }) => {
+                assert_eq!(rule, "multiple");
+                assert_eq!(path, "$.rules[1].args.base");
+            }
+            result => panic!("expected a missing rule argument error, got {:?}", result),
+        }
+
+        let describe = r#"{ "type": "date", "rules": [{ "name": "max", "args": {} }] }"#;
+        match crate::gen(describe.to_string()) {
+            Err(CodeGenError::MissingRuleArg { rule, path }) => {
+                assert_eq!(rule, "max");
+                assert_eq!(path, "$.rules[0].args.date");
+            }
+            result => panic!("expected a missing rule argument error, got {:?}", result),
+        }
+
+        let describe = r#"{ "type": "number", "rules": [{ "name": "max", "args": 10 }] }"#;
+        match crate::gen(describe.to_string()) {
+            Err(CodeGenError::MalformedDescribe { path }) => {
+                assert_eq!(path, "$.rules[0].args");
+            }
+            result => panic!("expected a malformed describe error, got {:?}", result),
+        }
+    }
+
     #[test]
     fn test_convert_simple_alternative() {
         let joi: JoiDescribe = serde_json::from_str(
@@ -651,22 +1343,64 @@ z.object({
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(
             tokens,
             Ok("z.union([z.number(), z.string()]).optional()".to_string())
         )
     }
 
+    #[test]
+    fn test_convert_alternative_match_modes() {
+        let describe = |mode: &str| {
+            format!(
+                r#"{{
+                    "type": "alternatives",
+                    "flags": {{ "match": "{mode}", "presence": "required" }},
+                    "matches": [
+                        {{ "schema": {{ "type": "number" }} }},
+                        {{ "schema": {{ "type": "number", "rules": [{{ "name": "integer" }}] }} }},
+                        {{ "schema": {{ "type": "number", "rules": [{{ "name": "min", "args": {{ "limit": 0 }} }}] }} }}
+                    ]
+                }}"#
+            )
+        };
+        let convert = |mode: &str| {
+            let joi: JoiDescribe = serde_json::from_str(&describe(mode)).unwrap();
+            joi.convert().unwrap()
+        };
+
+        assert_eq!(
+            convert("any"),
+            "z.union([z.number(), z.number().int(), z.number().min(0)])"
+        );
+        assert_eq!(
+            convert("all"),
+            "z.number().and(z.number().int()).and(z.number().min(0))"
+        );
+        assert_eq!(
+            convert("one"),
+            r#"z.union([z.number(), z.number().int(), z.number().min(0)]).superRefine((val, ctx) => {
+    if (val == null) {
+        return;
+    }
+    const matched = [z.number(), z.number().int(), z.number().min(0)].filter((schema) => schema.safeParse(val).success);
+    if (matched.length > 1) {
+        ctx.addIssue({ code: z.ZodIssueCode.custom, message: "Value matches more than one allowed type" });
+    }
+})"#
+        );
+    }
+
     #[test]
     fn test_convert_nullable_string() {
         let joi: JoiDescribe =
             serde_json::from_str("{\"type\":\"nullableString\",\"allow\":[null,\"\"]}").unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(
             tokens,
-            Ok("z.preprocess((val) => {\n    if (val === \"\") {\n        return null;\n    }\n    return val;\n}, z.string().nullable().optional())".to_string())
+            Ok("z.preprocess((val) => {\n    if (val === \"\") {\n        return null;\n    }\n    return val;\n}, z.string().nullable()).optional()".to_string())
         )
     }
 
@@ -674,11 +1408,8 @@ z.object({
     fn test_convert_unknown() {
         let joi: JoiDescribe = serde_json::from_str("{\"type\":\"someThingUnknown\"}").unwrap();
 
-        let tokens = joi.convert();
-        assert_eq!(
-            tokens,
-            Ok("z.someThingUnknown.__please_fix_me__().optional()".to_string())
-        )
+        let tokens = joi.convert().map_err(|e| e.to_string());
+        assert_eq!(tokens, Ok("z.any().optional()".to_string()))
     }
 
     #[test]
@@ -695,7 +1426,7 @@ z.object({
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(tokens, Ok("z.string().default(\"aStr\")".to_string()))
     }
 
@@ -727,7 +1458,7 @@ z.object({
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(
             tokens,
             Ok("z.number().int().min(10).max(200).optional()".to_string())
@@ -759,10 +1490,10 @@ z.object({
         )
         .unwrap();
 
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
         assert_eq!(
             tokens,
-            Ok("z.number().int().multiple.__please_fix_me__({\"base\":4}).somethingWeird.__please_fix_me__().optional()".to_string())
+            Ok("z.number().int().multipleOf(4).optional()".to_string())
         )
     }
 
@@ -787,11 +1518,11 @@ z.object({
         }"#;
 
         let joi: JoiDescribe = serde_json::from_str(describe).expect("should work...");
-        let tokens = joi.convert();
+        let tokens = joi.convert().map_err(|e| e.to_string());
 
         assert_eq!(
             tokens,
-            Ok("z.object({\n    password: z.string(),\n    username: z.string().optional().__please_handle_strip__()\n}).optional()".to_string())
+            Ok("z.object({\n    password: z.string(),\n    username: z.string().optional()\n}).optional()".to_string())
         )
     }
 }
@@ -14,6 +14,46 @@ pub struct JoiObject {
     joi_type: MustBe!("object"),
     #[serde(default)]
     pub keys: BTreeMap<String, JoiDescribe>,
+    /// Schemas for keys not listed in `keys`
+    #[serde(default)]
+    pub patterns: Vec<JoiObjectPattern>,
+    /// Relationships between keys, like `and()` or `with()`
+    #[serde(default)]
+    pub dependencies: Vec<JoiDependency>,
+}
+
+/// A dependency between the keys of an object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiDependency {
+    /// and | or | xor | nand | oxor | with | without
+    pub rel: String,
+    /// The main key for `with` and `without`
+    pub key: Option<String>,
+    /// The paths of the peer keys
+    #[serde(default)]
+    pub peers: Vec<String>,
+    #[serde(default)]
+    pub options: JoiDependencyOptions,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiDependencyOptions {
+    /// Separator for nested key paths, defaults to `.`
+    pub separator: Option<String>,
+}
+
+/// A `.pattern()` on an object, either the `regex` or `schema` is set to match the key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiObjectPattern {
+    /// The regex a key has to match
+    pub regex: Option<String>,
+    /// The schema a key has to match
+    pub schema: Option<JoiDescribe>,
+    /// The schema for the values of matching keys
+    pub rule: JoiDescribe,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -23,6 +63,9 @@ pub struct JoiArray {
     joi_type: MustBe!("array"),
     #[serde(default)]
     pub items: Vec<JoiDescribe>,
+    /// Items that must be in a specific position (tuple like)
+    #[serde(default)]
+    pub ordered: Vec<JoiDescribe>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -34,10 +77,15 @@ pub struct JoiAlternatives {
     pub matches: Vec<AltSchema>,
 }
 
+/// One of the matches of `Joi.alternatives()`
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct AltSchema {
-    pub schema: JoiDescribe,
+#[serde(untagged)]
+pub enum AltSchema {
+    /// A schema added with `.try()`
+    Schema { schema: Box<JoiDescribe> },
+    /// A match added with `.conditional()`
+    Conditional(Box<JoiWhen>),
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -45,8 +93,6 @@ pub struct AltSchema {
 pub struct JoiNumber {
     #[serde(rename = "type")]
     joi_type: MustBe!("number"),
-    #[serde(default)]
-    pub allow: Vec<serde_json::value::Number>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -54,8 +100,6 @@ pub struct JoiNumber {
 pub struct JoiString {
     #[serde(rename = "type")]
     joi_type: MustBe!("string"),
-    #[serde(default)]
-    pub allow: Vec<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -79,6 +123,41 @@ pub struct JoiAny {
     joi_type: MustBe!("any"),
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiBinary {
+    #[serde(rename = "type")]
+    joi_type: MustBe!("binary"),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiSymbol {
+    #[serde(rename = "type")]
+    joi_type: MustBe!("symbol"),
+    /// `[value, symbol]` pairs of values joi converts to symbols. Symbols can't be serialized so
+    /// only the values are known
+    #[serde(default)]
+    pub map: Vec<(serde_json::Value, serde_json::Value)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiFunction {
+    #[serde(rename = "type")]
+    joi_type: MustBe!("function"),
+}
+
+/// A `Joi.link()` to another schema, used for recursive schemas
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiLink {
+    #[serde(rename = "type")]
+    joi_type: MustBe!("link"),
+    /// The linked schema, `#id` for a schema with an `id()` or `/` for the root
+    pub link: Option<JoiRefDescribe>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct JoiUnknown {
@@ -89,6 +168,108 @@ pub struct JoiUnknown {
     pub unknown_fields: BTreeMap<String, serde_json::Value>,
 }
 
+/// A reference to another value, from `Joi.ref()`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiRef {
+    /// The keys to the referenced value
+    #[serde(default)]
+    pub path: Vec<String>,
+    /// How many levels up the path starts, 1 (the default) is a sibling and "root" is the top
+    pub ancestor: Option<serde_json::Value>,
+    /// value | global | local, global refs read from the `$context`
+    #[serde(rename = "type")]
+    pub ref_type: Option<String>,
+    /// If the referenced value is an array of values to match any of, from `Joi.in()`
+    #[serde(default, rename = "in")]
+    pub in_array: bool,
+}
+
+impl JoiRef {
+    /// How many objects up the referenced value starts from, if it is a value in the schema
+    pub fn ancestor_level(&self) -> Option<u64> {
+        if self.ref_type.as_deref().unwrap_or("value") != "value" || self.path.is_empty() {
+            return None;
+        }
+        match &self.ancestor {
+            Some(ancestor) => ancestor.as_u64().filter(|level| *level > 0),
+            None => Some(1),
+        }
+    }
+
+    /// The reference the way it is written in joi, like `a.b` or `$max`
+    pub fn display(&self) -> String {
+        let path = self.path.join(".");
+        match (self.ref_type.as_deref(), &self.ancestor) {
+            (Some("global"), _) => format!("${}", path),
+            (_, Some(serde_json::Value::String(root))) if root == "root" => format!("/{}", path),
+            (_, Some(ancestor)) => match ancestor.as_u64() {
+                Some(level) if level > 1 => format!("{}{}", ".".repeat(level as usize + 1), path),
+                _ => path,
+            },
+            _ => path,
+        }
+    }
+
+    /// If the reference points at a sibling of the value it is used on
+    pub fn is_sibling(&self) -> bool {
+        self.ref_type.as_deref().unwrap_or("value") == "value"
+            && match &self.ancestor {
+                Some(ancestor) => ancestor.as_u64() == Some(1),
+                None => true,
+            }
+            && !self.path.is_empty()
+    }
+}
+
+/// Joi describes references wrapped as `{ "ref": { ... } }`, the bare form is accepted as well
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JoiRefDescribe {
+    Wrapped {
+        #[serde(rename = "ref")]
+        reference: JoiRef,
+    },
+    Bare(JoiRef),
+}
+
+impl JoiRefDescribe {
+    pub fn reference(&self) -> &JoiRef {
+        match self {
+            JoiRefDescribe::Wrapped { reference } | JoiRefDescribe::Bare(reference) => reference,
+        }
+    }
+}
+
+/// A `.when()` condition on a schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiWhen {
+    /// The value the condition checks, the schema's own value if not set
+    #[serde(rename = "ref")]
+    pub reference: Option<JoiRefDescribe>,
+    /// The schema the value has to match for `then` to apply
+    pub is: Option<JoiDescribe>,
+    /// The schema the value has to not match for `then` to apply
+    pub not: Option<JoiDescribe>,
+    pub then: Option<JoiDescribe>,
+    pub otherwise: Option<JoiDescribe>,
+    /// Conditions checked in order, the first to match is applied
+    pub switch: Option<Vec<JoiWhenSwitch>>,
+    /// Stop checking the later conditions if this one matched
+    #[serde(default)]
+    pub r#break: bool,
+}
+
+/// One case of a `.when()` switch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoiWhenSwitch {
+    pub is: JoiDescribe,
+    pub then: Option<JoiDescribe>,
+    pub otherwise: Option<JoiDescribe>,
+}
+
 /// The type specific joi describe options
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -102,5 +283,9 @@ pub enum JoiDescribeType {
     String(JoiString),
     Boolean(JoiBoolean),
     Any(JoiAny),
+    Binary(JoiBinary),
+    Symbol(JoiSymbol),
+    Function(JoiFunction),
+    Link(JoiLink),
     Unknown(JoiUnknown),
 }
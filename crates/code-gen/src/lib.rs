@@ -1,8 +1,19 @@
+mod conditions;
+mod diagnostics;
+mod extensions;
 mod joi;
 mod joi_types;
+mod links;
+mod module;
+mod parse;
+mod refs;
+mod rules;
 use thiserror::Error;
 
-use crate::joi::JoiDescribe;
+pub use crate::diagnostics::{Diagnostic, DiagnosticKind};
+pub use crate::extensions::{Extension, ExtensionConfig, ExtensionHandler, Extensions};
+pub use crate::module::ModuleOptions;
+pub use crate::parse::path_span;
 
 #[derive(Error, Debug)]
 pub enum CodeGenError {
@@ -11,10 +22,159 @@ pub enum CodeGenError {
 
     #[error(transparent)]
     FormatError(#[from] std::fmt::Error),
+
+    /// A value in an `allow()`/`valid()` list that has no zod equivalent
+    #[error("unsupported allowed value {value} at {path}")]
+    UnsupportedAllowValue {
+        /// JSON path to the value in the describe output
+        path: String,
+        value: serde_json::Value,
+    },
+
+    /// Strict mode found parts of the joi schema that the zod schema would leave out
+    #[error("the joi schema can't be converted exactly:{}", list_diagnostics(.diagnostics))]
+    Lossy { diagnostics: Vec<Diagnostic> },
+
+    /// A presence flag other than `optional`, `required` or `forbidden`
+    #[error("unsupported presence {presence:?} at {path}")]
+    UnsupportedPresence {
+        /// JSON path to the flag in the describe output
+        path: String,
+        presence: String,
+    },
+
+    /// A rule without an argument it can't be converted without, like the limit of `min()`
+    #[error("missing argument of rule `{rule}` at {path}")]
+    MissingRuleArg {
+        rule: String,
+        /// JSON path to the missing argument in the describe output
+        path: String,
+    },
+
+    /// Part of the describe output doesn't have the shape joi gives it
+    #[error("malformed describe output at {path}")]
+    MalformedDescribe {
+        /// JSON path to the malformed value in the describe output
+        path: String,
+    },
+
+    /// A schema in the describe output that doesn't match its joi type
+    #[error("invalid joi {}schema at {path}: {message}", type_name(.joi_type))]
+    InvalidSchema {
+        /// JSON path to the schema in the describe output, like `$.keys.user.items[0]`
+        path: String,
+        /// The joi type the schema has, if it has one
+        joi_type: Option<String>,
+        message: String,
+    },
+}
+
+fn type_name(joi_type: &Option<String>) -> String {
+    match joi_type {
+        Some(joi_type) => format!("`{}` ", joi_type),
+        None => String::new(),
+    }
+}
+
+impl CodeGenError {
+    /// A stable code for the kind of error, like `joi_to_zod::missing_rule_arg`
+    pub fn code(&self) -> &'static str {
+        match self {
+            CodeGenError::ParseError(_) => "joi_to_zod::parse_error",
+            CodeGenError::FormatError(_) => "joi_to_zod::format_error",
+            CodeGenError::UnsupportedAllowValue { .. } => "joi_to_zod::unsupported_allow_value",
+            CodeGenError::Lossy { .. } => "joi_to_zod::lossy",
+            CodeGenError::UnsupportedPresence { .. } => "joi_to_zod::unsupported_presence",
+            CodeGenError::MissingRuleArg { .. } => "joi_to_zod::missing_rule_arg",
+            CodeGenError::MalformedDescribe { .. } => "joi_to_zod::malformed_describe",
+            CodeGenError::InvalidSchema { .. } => "joi_to_zod::invalid_schema",
+        }
+    }
+
+    /// Errors from converting a single schema have paths starting at that schema, this moves
+    /// them to `base`, the path of the schema
+    pub(crate) fn rebase(self, base: &str) -> Self {
+        let rebase = |path: String| match path.strip_prefix('$') {
+            Some(rest) => format!("{}{}", base, rest),
+            None => path,
+        };
+        match self {
+            CodeGenError::UnsupportedPresence { path, presence } => {
+                CodeGenError::UnsupportedPresence {
+                    path: rebase(path),
+                    presence,
+                }
+            }
+            CodeGenError::MissingRuleArg { rule, path } => CodeGenError::MissingRuleArg {
+                rule,
+                path: rebase(path),
+            },
+            CodeGenError::MalformedDescribe { path } => {
+                CodeGenError::MalformedDescribe { path: rebase(path) }
+            }
+            error => error,
+        }
+    }
+}
+
+fn list_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| format!("\n  - {}", diagnostic))
+        .collect()
+}
+
+/// Options to control how the zod schema is generated
+#[derive(Debug, Clone, Default)]
+pub struct GenOptions {
+    /// Use `z.unknown()` instead of `z.any()` for the elements of arrays without `items`
+    pub unknown_array_items: bool,
+    /// Converters for custom joi types and rules
+    pub extensions: Extensions,
+    /// Put a `// TODO` comment before each schema with parts that can't be converted, instead of
+    /// leaving them out silently
+    pub todo_comments: bool,
+    /// Fail with `CodeGenError::Lossy` instead of leaving out parts of the joi schema
+    pub strict: bool,
+    /// Generate a complete module exporting the schema, or each schema of a list of them
+    pub module: Option<ModuleOptions>,
+}
+
+/// The generated zod schema, and what of the joi schema it leaves out
+#[derive(Debug, Clone)]
+pub struct GenOutput {
+    pub code: String,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 pub fn gen(describe: String) -> Result<String, CodeGenError> {
-    let joi_str: JoiDescribe = serde_json::from_str((describe).as_str())?;
+    gen_with_options(describe, &GenOptions::default())
+}
+
+pub fn gen_with_options(describe: String, options: &GenOptions) -> Result<String, CodeGenError> {
+    Ok(gen_with_diagnostics(describe, options)?.code)
+}
 
-    Ok(joi_str.convert()?)
+pub fn gen_with_diagnostics(
+    describe: String,
+    options: &GenOptions,
+) -> Result<GenOutput, CodeGenError> {
+    let (code, diagnostics) = match &options.module {
+        Some(module_options) => {
+            let schemas = parse::parse_many(&describe)?;
+            for (path, schema) in &schemas {
+                schema.validate(path)?;
+            }
+            module::module(&schemas, module_options, options)?
+        }
+        None => {
+            let schema = parse::parse(&describe)?;
+            schema.validate("$")?;
+            schema.convert_with_diagnostics(options)?
+        }
+    };
+    if options.strict && !diagnostics.is_empty() {
+        return Err(CodeGenError::Lossy { diagnostics });
+    }
+    Ok(GenOutput { code, diagnostics })
 }
@@ -0,0 +1,621 @@
+use genco::prelude::js;
+use genco::prelude::*;
+use serde_json::Value;
+
+use crate::conditions;
+use crate::diagnostics::Conversion;
+use crate::joi::{JoiDescribe, JoiFlag, Tokenizer};
+use crate::joi_types::{JoiDescribeType, JoiLink, JoiRef, JoiRefDescribe};
+use crate::refs;
+use crate::rules;
+use crate::CodeGenError;
+
+// https://joi.dev/api/?v=17.9.1#linkref
+
+/// The schema a link points to
+enum LinkTarget {
+    /// `#id`, a schema with an `id()`
+    Id(String),
+    /// `/`, the schema being converted
+    Root,
+}
+
+/// The id the root schema is declared with when links point to it
+const ROOT_ID: &str = "root";
+
+fn link_target(link: &JoiLink) -> Option<LinkTarget> {
+    let reference = link.link.as_ref()?.reference();
+    match (reference.ref_type.as_deref(), reference.path.as_slice()) {
+        (Some("local"), [id]) => Some(LinkTarget::Id(id.clone())),
+        (_, []) if reference.ancestor == Some(Value::from("root")) => Some(LinkTarget::Root),
+        _ => None,
+    }
+}
+
+/// The name of the declaration for a schema id, `tree-node` is declared as `TreeNode`
+pub fn const_name(id: &str) -> String {
+    let name: String = id
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '$')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect::<String>()
+        })
+        .collect();
+    match name.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("_{}", name),
+        Some(_) => name,
+        None => "Schema".to_string(),
+    }
+}
+
+/// The link as it is written in joi when it points at something other than an id or the root
+pub fn unresolved_link(link: &JoiLink) -> Option<String> {
+    match link_target(link) {
+        Some(_) => None,
+        None => Some(
+            link.link
+                .as_ref()
+                .map(|link| link.reference().display())
+                .unwrap_or_default(),
+        ),
+    }
+}
+
+/// The schema id a link points at, like `node` for `#node`
+pub fn linked_id(link: &JoiLink) -> Option<String> {
+    match link_target(link) {
+        Some(LinkTarget::Id(id)) => Some(id),
+        _ => None,
+    }
+}
+
+/// A link resolves lazily since the linked schema can contain the link itself. Links that can't
+/// be resolved are reported by `Conversion::record`
+pub fn link_schema(link: &JoiLink) -> js::Tokens {
+    match link_target(link) {
+        Some(LinkTarget::Id(id)) => {
+            let name = const_name(&id);
+            quote! { z.lazy(() => $name) }
+        }
+        Some(LinkTarget::Root) => {
+            let name = const_name(ROOT_ID);
+            quote! { z.lazy(() => $name) }
+        }
+        None => quote! { z.any() },
+    }
+}
+
+/// Where a schema with an id is used, its declaration with the presence of the use
+pub fn declared_schema(
+    id: &str,
+    schema: &JoiDescribe,
+    default_optional: bool,
+    options: &Conversion,
+) -> Result<js::Tokens, CodeGenError> {
+    let name = const_name(id);
+    let flags = JoiFlag {
+        presence: schema.flags.presence.clone(),
+        default: schema.flags.default.clone(),
+        ..JoiFlag::default()
+    };
+    let flags = flags.to_tokens(default_optional, options)?;
+    if flags.is_empty() {
+        Ok(quote! { $name })
+    } else {
+        Ok(quote! { $name.$flags })
+    }
+}
+
+/// The schemas with an id, nested ones first so they are declared before they are used
+fn collect_declared<'a>(schema: &'a JoiDescribe, declared: &mut Vec<&'a JoiDescribe>) {
+    for (_, child) in schema.children() {
+        collect_declared(child, declared);
+    }
+    if let Some(id) = &schema.flags.id {
+        if !declared
+            .iter()
+            .any(|other| other.flags.id.as_ref() == Some(id))
+        {
+            declared.push(schema);
+        }
+    }
+}
+
+/// The ids links in a schema point at that no schema in it declares. Joi fails to resolve them,
+/// they are declared as `z.any()` so the generated code still runs
+pub fn undeclared_ids(root: &JoiDescribe) -> Vec<String> {
+    let mut declared = Vec::new();
+    collect_declared(root, &mut declared);
+    let mut undeclared = Vec::new();
+    collect_linked_ids(root, &mut undeclared);
+    undeclared.retain(|id| {
+        !declared
+            .iter()
+            .any(|schema| schema.flags.id.as_ref() == Some(id))
+    });
+    undeclared
+}
+
+fn collect_linked_ids(schema: &JoiDescribe, ids: &mut Vec<String>) {
+    if let JoiDescribeType::Link(link) = &schema.type_options {
+        if let Some(id) = linked_id(link) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    for (_, child) in schema.children() {
+        collect_linked_ids(child, ids);
+    }
+}
+
+/// A copy of a schema with the ids `rename` returns a new id for renamed, along with the links
+/// to them. Renaming the root id turns the links to the root into links to the root's id
+pub fn rename_ids(root: &JoiDescribe, rename: &dyn Fn(&str) -> Option<String>) -> JoiDescribe {
+    let mut renamed = root.clone();
+    let root_id = match rename(ROOT_ID) {
+        Some(id) if links_to_root(root) => {
+            let root_id = root
+                .flags
+                .id
+                .as_deref()
+                .map(|id| rename(id).unwrap_or(id.to_string()));
+            let root_id = root_id.unwrap_or(id);
+            renamed.flags.id = Some(root_id.clone());
+            Some(root_id)
+        }
+        _ => None,
+    };
+    rename_in(&mut renamed, rename, root_id.as_deref());
+    if let Some(root_id) = root_id {
+        renamed.flags.id = Some(root_id);
+    }
+    renamed
+}
+
+fn rename_in(
+    schema: &mut JoiDescribe,
+    rename: &dyn Fn(&str) -> Option<String>,
+    root_id: Option<&str>,
+) {
+    if let Some(id) = schema.flags.id.as_deref().and_then(rename) {
+        schema.flags.id = Some(id);
+    }
+    if let JoiDescribeType::Link(link) = &mut schema.type_options {
+        let target = match link_target(link) {
+            Some(LinkTarget::Id(id)) => rename(&id),
+            Some(LinkTarget::Root) => root_id.map(str::to_string),
+            None => None,
+        };
+        if let Some(id) = target {
+            link.link = Some(JoiRefDescribe::Bare(JoiRef {
+                path: vec![id],
+                ancestor: None,
+                ref_type: Some("local".to_string()),
+                in_array: false,
+            }));
+        }
+    }
+    for child in schema.children_mut() {
+        rename_in(child, rename, root_id);
+    }
+}
+
+fn links_to_root(schema: &JoiDescribe) -> bool {
+    let is_root_link = match &schema.type_options {
+        JoiDescribeType::Link(link) => matches!(link_target(link), Some(LinkTarget::Root)),
+        _ => false,
+    };
+    is_root_link
+        || schema
+            .children()
+            .iter()
+            .any(|(_, child)| links_to_root(child))
+}
+
+/// A `const` for a schema with an id. zod can't infer the type of a recursive schema so in
+/// TypeScript the declaration has an explicit type. Its input is `unknown` since defaults and
+/// conversions make it differ from the output
+fn declaration(
+    schema: &JoiDescribe,
+    options: &Conversion,
+    typescript: bool,
+) -> Result<(String, js::Tokens), CodeGenError> {
+    let name = const_name(schema.flags.id.as_deref().unwrap_or_default());
+    // the presence and default are applied where the schema is used
+    let mut body = schema.clone();
+    body.flags.id = None;
+    body.flags.presence = None;
+    body.flags.default = None;
+    let ts_type = ts_type(&body, options);
+    let body = body.to_tokens(false, options)?;
+    let tokens = match typescript {
+        true => quote! {
+            type $(&name) = $ts_type;
+            const $(&name): z.ZodType<$(&name), z.ZodTypeDef, unknown> = $body;
+        },
+        false => quote! {
+            const $(&name) = $body;
+        },
+    };
+    Ok((name, tokens))
+}
+
+/// The converted schema, and the declarations of the schemas with an id it uses by name
+pub fn declarations(
+    root: &JoiDescribe,
+    options: &Conversion,
+    typescript: bool,
+) -> Result<(Vec<(String, js::Tokens)>, js::Tokens), CodeGenError> {
+    let mut declared = Vec::new();
+    collect_declared(root, &mut declared);
+    let mut declarations = declared
+        .into_iter()
+        .map(|schema| declaration(schema, options, typescript))
+        .collect::<Result<Vec<_>, _>>()?;
+    // reported by `Conversion::record`
+    for id in undeclared_ids(root) {
+        let name = const_name(&id);
+        let tokens = match typescript {
+            true => quote! {
+                type $(&name) = any;
+                const $(&name) = z.any();
+            },
+            false => quote! {
+                const $(&name) = z.any();
+            },
+        };
+        declarations.push((name, tokens));
+    }
+    let schema = match (&root.flags.id, links_to_root(root)) {
+        (Some(id), true) => {
+            let root_name = const_name(ROOT_ID);
+            let name = const_name(id);
+            let tokens = match typescript {
+                true => quote! {
+                    type $(&root_name) = $(&name);
+                    const $(&root_name): z.ZodType<$(&root_name), z.ZodTypeDef, unknown> = $name;
+                },
+                false => quote! {
+                    const $(&root_name) = $name;
+                },
+            };
+            declarations.push((root_name, tokens));
+            root.to_tokens(true, options)?
+        }
+        (None, true) => {
+            let mut named = root.clone();
+            named.flags.id = Some(ROOT_ID.to_string());
+            declarations.push(declaration(&named, options, typescript)?);
+            named.to_tokens(true, options)?
+        }
+        (_, false) => root.to_tokens(true, options)?,
+    };
+    Ok((declarations, schema))
+}
+
+/// The converted schema, preceded by the declarations of the schemas with an id
+pub fn with_declarations(
+    root: &JoiDescribe,
+    options: &Conversion,
+) -> Result<js::Tokens, CodeGenError> {
+    let (declarations, schema) = declarations(root, options, true)?;
+    if declarations.is_empty() {
+        return Ok(schema);
+    }
+    Ok(quote! {
+        $(for (_, declaration) in declarations join ($['\n'])=> $declaration)
+        $['\n']
+        $schema
+    })
+}
+
+/// The TypeScript type of the values a schema parses to
+fn ts_type(schema: &JoiDescribe, options: &Conversion) -> js::Tokens {
+    if let Some(id) = &schema.flags.id {
+        let name = const_name(id);
+        return quote! { $name };
+    }
+
+    let literals: Vec<String> = refs::allow_literals(&schema.allow)
+        .iter()
+        .filter(|value| !value.is_object() && !value.is_array())
+        .map(|value| value.to_string())
+        .collect();
+    if schema.flags.only && !literals.is_empty() {
+        return quote! { $(for literal in literals join ( | )=> $literal) };
+    }
+
+    let ts_type = match &schema.type_options {
+        JoiDescribeType::Object(_) if rules::is_instance(schema) => {
+            match rules::instance_of(schema) {
+                Some(class) => quote! { $class },
+                None => quote! { unknown },
+            }
+        }
+        JoiDescribeType::Object(object) if object.keys.is_empty() => {
+            match object.patterns.as_slice() {
+                [pattern] => {
+                    let value = ts_type(&pattern.rule, options);
+                    quote! { Record<string, $value> }
+                }
+                _ => quote! { Record<string, unknown> },
+            }
+        }
+        JoiDescribeType::Object(object) => {
+            let keys = object.keys.iter().map(|(key, child)| {
+                let key = ts_key(key);
+                let child_type = ts_type(child, options);
+                let required = child.flags.default.is_some()
+                    || child.flags.presence.as_deref() == Some("required");
+                match child.flags.presence.as_deref() {
+                    Some("forbidden") => quote! { $key?: undefined },
+                    _ if required => quote! { $key: $child_type },
+                    _ => quote! { $key?: $child_type },
+                }
+            });
+            quote! {
+                {
+                    $(for key in keys join (;$['\r'])=> $key)
+                }
+            }
+        }
+        JoiDescribeType::Array(array) => {
+            let items: Vec<js::Tokens> = array
+                .items
+                .iter()
+                .map(|item| ts_type(item, options))
+                .collect();
+            let element = match items.as_slice() {
+                [] if options.unknown_array_items => quote! { unknown },
+                [] => quote! { any },
+                [item] => item.clone(),
+                items => quote! { ($(for item in items join ( | )=> $item)) },
+            };
+            if array.ordered.is_empty() {
+                quote! { $element[] }
+            } else {
+                // the items up to the last required one are there, the others can be left out
+                let min_length = rules::ordered_min_length(array);
+                let ordered = array.ordered.iter().enumerate().map(|(index, item)| {
+                    let item_type = ts_type(item, options);
+                    let required = item.flags.presence.as_deref() == Some("required");
+                    match (index < min_length, required) {
+                        (true, true) => item_type,
+                        (true, false) => quote! { $item_type | undefined },
+                        (false, _) => quote! { $item_type? },
+                    }
+                });
+                let rest = match items.is_empty() {
+                    true => None,
+                    false => Some(quote! { ...$element[] }),
+                };
+                quote! { [$(for item in ordered.chain(rest) join (, )=> $item)] }
+            }
+        }
+        JoiDescribeType::Alternatives(alt) => {
+            let options: Vec<js::Tokens> = conditions::alternative_options(alt)
+                .into_iter()
+                .map(|option| ts_type(option, options))
+                .collect();
+            let separator = match schema.flags.r#match.as_deref() {
+                Some("all") => " & ",
+                _ => " | ",
+            };
+            match options.is_empty() {
+                true => quote! { never },
+                false => quote! { $(for option in options join ($separator)=> $option) },
+            }
+        }
+        JoiDescribeType::Date(_) => quote! { Date },
+        JoiDescribeType::Number(_) => quote! { number },
+        JoiDescribeType::String(_) => quote! { string },
+        JoiDescribeType::Boolean(_) => quote! { boolean },
+        JoiDescribeType::Any(_) => quote! { any },
+        JoiDescribeType::Binary(_) => quote! { Buffer },
+        JoiDescribeType::Symbol(symbol) => {
+            let values = rules::symbol_map_literals(symbol, schema);
+            quote! { symbol$(for value in values => $[' ']| $value) }
+        }
+        JoiDescribeType::Function(_) => quote! { (...args: any[]) => any },
+        JoiDescribeType::Link(link) => match link_target(link) {
+            Some(LinkTarget::Id(id)) => {
+                let name = const_name(&id);
+                quote! { $name }
+            }
+            Some(LinkTarget::Root) => {
+                let name = const_name(ROOT_ID);
+                quote! { $name }
+            }
+            None => quote! { unknown },
+        },
+        JoiDescribeType::Unknown(_) => quote! { unknown },
+    };
+
+    // the values of `allow()` the zod schema adds as literals
+    let extras = rules::allow_extra_literals(schema);
+    let ts_type = quote! { $ts_type$(for extra in extras => $[' ']| $extra) };
+
+    if schema.allow.iter().any(Value::is_null) {
+        quote! { $ts_type | null }
+    } else {
+        ts_type
+    }
+}
+
+/// A key of an object type, quoted unless it is a valid identifier
+fn ts_key(key: &str) -> js::Tokens {
+    let identifier = key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && key.starts_with(|c: char| !c.is_ascii_digit());
+    match identifier {
+        true => quote! { $key },
+        false => quote! { $[str]($[const](key)) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::joi::JoiDescribe;
+    use crate::{gen_with_diagnostics, DiagnosticKind, GenOptions};
+
+    fn convert(describe: &str) -> String {
+        let joi: JoiDescribe = serde_json::from_str(describe).expect("should work...");
+        joi.convert().unwrap()
+    }
+
+    #[test]
+    fn test_recursive_id() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "keys": {
+                        "tree": {
+                            "type": "object",
+                            "flags": { "id": "tree-node", "presence": "required" },
+                            "keys": {
+                                "value": { "type": "number", "flags": { "presence": "required" } },
+                                "children": { "type": "array", "items": [{ "type": "link", "link": { "ref": { "path": ["tree-node"], "type": "local" } } }] }
+                            }
+                        }
+                    }
+                }"#
+            ),
+            r#"type TreeNode = {
+    children?: TreeNode[];
+    value: number
+};
+const TreeNode: z.ZodType<TreeNode, z.ZodTypeDef, unknown> = z.object({
+    children: z.array(z.lazy(() => TreeNode)).optional(),
+    value: z.number()
+});
+
+z.object({
+    tree: TreeNode
+}).optional()"#
+        );
+    }
+
+    #[test]
+    fn test_root_link() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "keys": {
+                        "name": { "type": "string" },
+                        "parent": { "type": "link", "link": { "ref": { "path": [], "ancestor": "root" } } }
+                    }
+                }"#
+            ),
+            r#"type Root = {
+    name?: string;
+    parent?: Root
+};
+const Root: z.ZodType<Root, z.ZodTypeDef, unknown> = z.object({
+    name: z.string().optional(),
+    parent: z.lazy(() => Root).optional()
+});
+
+Root.optional()"#
+        );
+    }
+
+    #[test]
+    fn test_shared() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "array",
+                    "shared": [{ "type": "string", "flags": { "id": "name" }, "rules": [{ "name": "min", "args": { "limit": 1 } }] }],
+                    "items": [
+                        { "type": "link", "link": { "ref": { "path": ["name"], "type": "local" } } },
+                        { "type": "link", "link": { "ref": { "path": ["missing", "deep"], "type": "local" } } }
+                    ]
+                }"#
+            ),
+            r#"type Name = string;
+const Name: z.ZodType<Name, z.ZodTypeDef, unknown> = z.string().min(1);
+
+z.array(z.union([z.lazy(() => Name), z.any()])).optional()"#
+        );
+    }
+
+    #[test]
+    fn test_ordered_type() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "array",
+                    "flags": { "id": "pair" },
+                    "ordered": [
+                        { "type": "string" },
+                        { "type": "number", "flags": { "presence": "required" } },
+                        { "type": "link", "link": { "ref": { "path": ["pair"], "type": "local" } } }
+                    ]
+                }"#
+            ),
+            r#"type Pair = [string | undefined, number, Pair?];
+const Pair: z.ZodType<Pair, z.ZodTypeDef, unknown> = z.union([z.tuple([z.string().optional(), z.number()]), z.tuple([z.string().optional(), z.number(), z.lazy(() => Pair).optional()])]);
+
+Pair.optional()"#
+        );
+    }
+
+    #[test]
+    fn test_allowed_values_type() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "id": "score" },
+                    "keys": {
+                        "points": { "type": "number", "allow": ["none", null] },
+                        "rank": { "type": "symbol", "map": [["first", { "symbol": "first" }]] }
+                    }
+                }"#
+            ),
+            r#"type Score = {
+    points?: number | "none" | null;
+    rank?: symbol | "first"
+};
+const Score: z.ZodType<Score, z.ZodTypeDef, unknown> = z.object({
+    points: z.union([z.number(), z.literal("none")]).nullable().optional(),
+    rank: z.union([z.symbol(), z.literal("first")]).optional()
+});
+
+Score.optional()"#
+        );
+    }
+
+    #[test]
+    fn test_undeclared_link() {
+        let describe = r#"{
+            "type": "object",
+            "keys": { "next": { "type": "link", "link": { "ref": { "path": ["nope"], "type": "local" } } } }
+        }"#;
+        let output = gen_with_diagnostics(describe.to_string(), &GenOptions::default()).unwrap();
+        assert_eq!(
+            output.code,
+            r#"type Nope = any;
+const Nope = z.any();
+
+z.object({
+    next: z.lazy(() => Nope).optional()
+}).optional()"#
+        );
+        let found: Vec<(DiagnosticKind, &str, &str)> = output
+            .diagnostics
+            .iter()
+            .map(|d| (d.kind, d.path.as_str(), d.name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(DiagnosticKind::UnresolvedLink, "$.keys.next", "#nope")]
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use genco::prelude::js;
+use genco::prelude::*;
+
+use crate::diagnostics::{Conversion, Diagnostic};
+use crate::joi::JoiDescribe;
+use crate::links;
+use crate::{CodeGenError, GenOptions};
+
+/// How a complete module is generated, instead of a bare zod expression
+#[derive(Debug, Clone)]
+pub struct ModuleOptions {
+    /// Where `z` is imported from
+    pub zod_import: String,
+    /// Generate TypeScript, with type annotations on the declarations of recursive schemas.
+    /// Without it the module is plain JavaScript
+    pub typescript: bool,
+    /// Export the inferred type of each schema, like `export type User = z.infer<typeof
+    /// UserSchema>`. Only in TypeScript
+    pub export_types: bool,
+}
+
+impl Default for ModuleOptions {
+    fn default() -> Self {
+        ModuleOptions {
+            zod_import: "zod".to_string(),
+            typescript: true,
+            export_types: false,
+        }
+    }
+}
+
+/// The name a schema is exported by, from the `className` of its metas or else its id
+fn export_name(schema: &JoiDescribe, index: usize, count: usize) -> String {
+    let class_name = schema
+        .metas
+        .iter()
+        .find_map(|meta| meta.get("className").and_then(|name| name.as_str()));
+    match (class_name.or(schema.flags.id.as_deref()), count) {
+        (Some(name), _) => links::const_name(name),
+        (None, 1) => "Unnamed".to_string(),
+        (None, _) => format!("Unnamed{}", index + 1),
+    }
+}
+
+/// The declarations of a schema and the schema using them. Declarations with the name of a
+/// different earlier one are renamed with a number, like `Friend2`, identical ones are shared
+fn schema_declarations(
+    schema: &JoiDescribe,
+    declared: &[(String, js::Tokens)],
+    module: &ModuleOptions,
+    options: &Conversion,
+) -> Result<(Vec<(String, js::Tokens)>, js::Tokens), CodeGenError> {
+    let (declarations, tokens) = links::declarations(schema, options, module.typescript)?;
+    let conflicts: Vec<&String> = declarations
+        .iter()
+        .filter(|(name, declaration)| {
+            declared
+                .iter()
+                .any(|(other, other_declaration)| other == name && other_declaration != declaration)
+        })
+        .map(|(name, _)| name)
+        .collect();
+    if conflicts.is_empty() {
+        return Ok((declarations, tokens));
+    }
+
+    let taken = |name: &String| {
+        declared.iter().any(|(other, _)| other == name)
+            || declarations.iter().any(|(other, _)| other == name)
+    };
+    let rename = |id: &str| -> Option<String> {
+        if !conflicts.contains(&&links::const_name(id)) {
+            return None;
+        }
+        (2..)
+            .map(|n| format!("{}-{}", id, n))
+            .find(|renamed| !taken(&links::const_name(renamed)))
+    };
+    let renamed = links::rename_ids(schema, &rename);
+    links::declarations(&renamed, options, module.typescript)
+}
+
+/// A module importing zod and exporting a `<Name>Schema` for each schema. Schemas with an id are
+/// declared once, before the exports. A name that is already exported or declared gets a number,
+/// like `User2`
+pub fn module(
+    schemas: &[(String, JoiDescribe)],
+    module: &ModuleOptions,
+    options: &GenOptions,
+) -> Result<(String, Vec<Diagnostic>), CodeGenError> {
+    let schemas: Vec<JoiDescribe> = schemas
+        .iter()
+        .map(|(path, schema)| {
+            let mut schema = schema.clone();
+            schema.set_paths(path);
+            schema
+        })
+        .collect();
+    let conversion = Conversion::new(options, &schemas.iter().collect::<Vec<_>>());
+
+    let mut declarations: Vec<(String, js::Tokens)> = Vec::new();
+    let mut schema_tokens: Vec<js::Tokens> = Vec::new();
+    for schema in &schemas {
+        let (schema_declarations, tokens) =
+            schema_declarations(schema, &declarations, module, &conversion)?;
+        for (name, declaration) in schema_declarations {
+            if !declarations.iter().any(|(other, _)| *other == name) {
+                declarations.push((name, declaration));
+            }
+        }
+        schema_tokens.push(tokens);
+    }
+    let declared: Vec<&String> = declarations.iter().map(|(name, _)| name).collect();
+
+    let export_types = module.typescript && module.export_types;
+    let mut exported: Vec<String> = Vec::new();
+    let mut exports: Vec<js::Tokens> = Vec::new();
+    for (index, (schema, tokens)) in schemas.iter().zip(schema_tokens).enumerate() {
+        let id_name = schema.flags.id.as_deref().map(links::const_name);
+        let self_declared = |name: &str| id_name.as_deref() == Some(name);
+        // the declarations have a `const` and, in TypeScript, a `type` of their name
+        let taken = |name: &String| {
+            exported.contains(name)
+                || declared.contains(&&format!("{}Schema", name))
+                || (export_types && declared.contains(&name) && !self_declared(name))
+        };
+        let base = export_name(schema, index, schemas.len());
+        let name = std::iter::once(base.clone())
+            .chain((2..).map(|n| format!("{}{}", base, n)))
+            .find(|name| !taken(name))
+            .unwrap_or(base);
+        exported.push(name.clone());
+
+        let schema_name = format!("{}Schema", name);
+        exports.push(quote! {
+            export const $(&schema_name) = $tokens;
+        });
+        match (export_types, self_declared(&name)) {
+            // a schema with an id is declared with its type already
+            (true, true) => exports.push(quote! {
+                export type { $name };
+            }),
+            (true, false) => exports.push(quote! {
+                export type $name = z.infer<typeof $schema_name>;
+            }),
+            (false, _) => {}
+        }
+    }
+
+    let tokens: js::Tokens = quote! {
+        import { z } from $[str]($[const](&module.zod_import));
+        $['\n']
+        $(for (_, declaration) in declarations => $declaration$['\n'])
+        $(for export in exports join ($['\r'])=> $export)
+    };
+    Ok((tokens.to_file_string()?, conversion.into_diagnostics()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ModuleOptions;
+    use crate::{gen_with_options, GenOptions};
+
+    fn convert(describe: &str, module: ModuleOptions) -> String {
+        let options = GenOptions {
+            module: Some(module),
+            ..GenOptions::default()
+        };
+        gen_with_options(describe.to_string(), &options).unwrap()
+    }
+
+    const DESCRIBE: &str = r#"[
+        {
+            "type": "object",
+            "flags": { "presence": "required" },
+            "metas": [{ "className": "User" }],
+            "keys": {
+                "name": { "type": "string" },
+                "friends": { "type": "array", "items": [{ "type": "link", "link": { "ref": { "path": ["friend"], "type": "local" } } }] }
+            },
+            "shared": [{ "type": "object", "flags": { "id": "friend" }, "keys": { "name": { "type": "string" } } }]
+        },
+        {
+            "type": "object",
+            "flags": { "id": "tree" },
+            "keys": {
+                "children": { "type": "array", "items": [{ "type": "link", "link": { "ref": { "path": ["tree"], "type": "local" } } }] }
+            }
+        }
+    ]"#;
+
+    #[test]
+    fn test_typescript_module() {
+        let module = ModuleOptions {
+            zod_import: "zod/v3".to_string(),
+            export_types: true,
+            ..ModuleOptions::default()
+        };
+        assert_eq!(
+            convert(DESCRIBE, module),
+            r#"import { z } from "zod/v3";
+
+type Friend = {
+    name?: string
+};
+const Friend: z.ZodType<Friend, z.ZodTypeDef, unknown> = z.object({
+    name: z.string().optional()
+});
+
+type Tree = {
+    children?: Tree[]
+};
+const Tree: z.ZodType<Tree, z.ZodTypeDef, unknown> = z.object({
+    children: z.array(z.lazy(() => Tree)).optional()
+});
+
+export const UserSchema = z.object({
+    friends: z.array(z.lazy(() => Friend)).optional(),
+    name: z.string().optional()
+});
+export type User = z.infer<typeof UserSchema>;
+export const TreeSchema = Tree.optional();
+export type { Tree };
+"#
+        );
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let describe = r#"[
+            { "type": "string", "metas": [{ "className": "A" }] },
+            { "type": "number", "metas": [{ "className": "A" }] },
+            {
+                "type": "object",
+                "metas": [{ "className": "Friend" }],
+                "keys": { "best": { "type": "link", "link": { "ref": { "path": ["friend"], "type": "local" } } } },
+                "shared": [{ "type": "string", "flags": { "id": "friend" } }]
+            }
+        ]"#;
+        let module = ModuleOptions {
+            export_types: true,
+            ..ModuleOptions::default()
+        };
+        assert_eq!(
+            convert(describe, module),
+            r#"import { z } from "zod";
+
+type Friend = string;
+const Friend: z.ZodType<Friend, z.ZodTypeDef, unknown> = z.string();
+
+export const ASchema = z.string().optional();
+export type A = z.infer<typeof ASchema>;
+export const A2Schema = z.number().optional();
+export type A2 = z.infer<typeof A2Schema>;
+export const Friend2Schema = z.object({
+    best: z.lazy(() => Friend).optional()
+}).optional();
+export type Friend2 = z.infer<typeof Friend2Schema>;
+"#
+        );
+    }
+
+    #[test]
+    fn test_conflicting_declarations() {
+        let describe = r#"[
+            {
+                "type": "object",
+                "metas": [{ "className": "A" }],
+                "keys": {
+                    "a": { "type": "string" },
+                    "next": { "type": "link", "link": { "ref": { "path": [], "ancestor": "root" } } },
+                    "friend": { "type": "link", "link": { "ref": { "path": ["friend"], "type": "local" } } }
+                },
+                "shared": [{ "type": "string", "flags": { "id": "friend" } }]
+            },
+            {
+                "type": "object",
+                "metas": [{ "className": "B" }],
+                "keys": {
+                    "b": { "type": "number" },
+                    "next": { "type": "link", "link": { "ref": { "path": [], "ancestor": "root" } } },
+                    "friend": { "type": "link", "link": { "ref": { "path": ["friend"], "type": "local" } } }
+                },
+                "shared": [{ "type": "number", "flags": { "id": "friend" } }]
+            },
+            {
+                "type": "object",
+                "metas": [{ "className": "C" }],
+                "keys": { "friend": { "type": "link", "link": { "ref": { "path": ["friend"], "type": "local" } } } },
+                "shared": [{ "type": "string", "flags": { "id": "friend" } }]
+            }
+        ]"#;
+        assert_eq!(
+            convert(describe, ModuleOptions::default()),
+            r#"import { z } from "zod";
+
+type Friend = string;
+const Friend: z.ZodType<Friend, z.ZodTypeDef, unknown> = z.string();
+
+type Root = {
+    a?: string;
+    friend?: Friend;
+    next?: Root
+};
+const Root: z.ZodType<Root, z.ZodTypeDef, unknown> = z.object({
+    a: z.string().optional(),
+    friend: z.lazy(() => Friend).optional(),
+    next: z.lazy(() => Root).optional()
+});
+
+type Friend2 = number;
+const Friend2: z.ZodType<Friend2, z.ZodTypeDef, unknown> = z.number();
+
+type Root2 = {
+    b?: number;
+    friend?: Friend2;
+    next?: Root2
+};
+const Root2: z.ZodType<Root2, z.ZodTypeDef, unknown> = z.object({
+    b: z.number().optional(),
+    friend: z.lazy(() => Friend2).optional(),
+    next: z.lazy(() => Root2).optional()
+});
+
+export const ASchema = Root.optional();
+export const BSchema = Root2.optional();
+export const CSchema = z.object({
+    friend: z.lazy(() => Friend).optional()
+}).optional();
+"#
+        );
+    }
+
+    #[test]
+    fn test_javascript_module() {
+        let module = ModuleOptions {
+            typescript: false,
+            export_types: true,
+            ..ModuleOptions::default()
+        };
+        assert_eq!(
+            convert(DESCRIBE, module),
+            r#"import { z } from "zod";
+
+const Friend = z.object({
+    name: z.string().optional()
+});
+
+const Tree = z.object({
+    children: z.array(z.lazy(() => Tree)).optional()
+});
+
+export const UserSchema = z.object({
+    friends: z.array(z.lazy(() => Friend)).optional(),
+    name: z.string().optional()
+});
+export const TreeSchema = Tree.optional();
+"#
+        );
+        assert_eq!(
+            convert(r#"{ "type": "string" }"#, ModuleOptions::default()),
+            "import { z } from \"zod\";\n\nexport const UnnamedSchema = z.string().optional();\n"
+        );
+    }
+}
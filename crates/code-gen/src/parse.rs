@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use miette::SourceSpan;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use crate::joi::{key_segment, JoiDescribe};
+use crate::joi_types::{
+    JoiAlternatives, JoiAny, JoiArray, JoiBinary, JoiBoolean, JoiDate, JoiDescribeType,
+    JoiFunction, JoiLink, JoiNumber, JoiObject, JoiString, JoiSymbol,
+};
+use crate::CodeGenError;
+
+/// The joi types with their own struct, a schema of one of these types that doesn't match it
+/// would be read as a custom type
+const KNOWN_TYPES: &[&str] = &[
+    "object",
+    "array",
+    "alternatives",
+    "date",
+    "number",
+    "string",
+    "boolean",
+    "any",
+    "binary",
+    "symbol",
+    "function",
+    "link",
+];
+
+/// Parse the describe output. A schema that doesn't match its joi type is reported with its JSON
+/// path, the deepest one first, instead of serde's "data did not match any variant"
+pub fn parse(describe: &str) -> Result<JoiDescribe, CodeGenError> {
+    let value: Value = serde_json::from_str(describe)?;
+    parse_schema(&value, "$")
+}
+
+/// Parse the describe output of a schema, or a list of them, with the JSON path of each schema
+pub fn parse_many(describe: &str) -> Result<Vec<(String, JoiDescribe)>, CodeGenError> {
+    let value: Value = serde_json::from_str(describe)?;
+    let Value::Array(values) = value else {
+        return Ok(vec![("$".to_string(), parse_schema(&value, "$")?)]);
+    };
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let path = format!("$[{}]", index);
+            let schema = parse_schema(value, &path)?;
+            Ok((path, schema))
+        })
+        .collect()
+}
+
+/// The tree is deserialized once, it is only walked to find the schema at fault when that fails
+fn parse_schema(value: &Value, path: &str) -> Result<JoiDescribe, CodeGenError> {
+    let error = match JoiDescribe::deserialize(value) {
+        Ok(schema) if !misread_type(&schema) => return Ok(schema),
+        Ok(_) => None,
+        Err(error) => Some(error),
+    };
+    check_schema(value, path)?;
+    // the walk finds every error the deserializing does, this is only a fallback
+    Err(match error {
+        Some(error) => invalid(path, None, error),
+        None => invalid(path, None, "a known joi type was read as a custom type"),
+    })
+}
+
+/// If a schema, or a schema nested in it, has a known type but was read as a custom type
+fn misread_type(schema: &JoiDescribe) -> bool {
+    let misread = match &schema.type_options {
+        JoiDescribeType::Unknown(unknown) => KNOWN_TYPES.contains(&unknown.joi_type.as_str()),
+        _ => false,
+    };
+    misread
+        || schema
+            .children()
+            .iter()
+            .any(|(_, child)| misread_type(child))
+}
+
+fn invalid(path: &str, joi_type: Option<&str>, message: impl ToString) -> CodeGenError {
+    CodeGenError::InvalidSchema {
+        path: path.to_string(),
+        joi_type: joi_type.map(str::to_string),
+        message: message.to_string(),
+    }
+}
+
+/// Find the deepest schema that doesn't match its joi type
+fn check_schema(value: &Value, path: &str) -> Result<(), CodeGenError> {
+    if !value.is_object() {
+        return Err(invalid(path, None, "expected a described joi schema"));
+    }
+    let joi_type = match value.get("type") {
+        Some(Value::String(joi_type)) => joi_type.as_str(),
+        Some(_) => return Err(invalid(path, None, "`type` is not a string")),
+        None => return Err(invalid(path, None, "missing field `type`")),
+    };
+
+    for (child_path, child) in child_schemas(value, path) {
+        check_schema(child, &child_path)?;
+    }
+
+    let checked = match joi_type {
+        "object" => check_type::<JoiObject>(value),
+        "array" => check_type::<JoiArray>(value),
+        "alternatives" => check_type::<JoiAlternatives>(value),
+        "date" => check_type::<JoiDate>(value),
+        "number" => check_type::<JoiNumber>(value),
+        "string" => check_type::<JoiString>(value),
+        "boolean" => check_type::<JoiBoolean>(value),
+        "any" => check_type::<JoiAny>(value),
+        "binary" => check_type::<JoiBinary>(value),
+        "symbol" => check_type::<JoiSymbol>(value),
+        "function" => check_type::<JoiFunction>(value),
+        "link" => check_type::<JoiLink>(value),
+        _ => Ok(()),
+    };
+    checked
+        .and_then(|_| check_type::<JoiDescribe>(value))
+        .map_err(|error| invalid(path, Some(joi_type), error))
+}
+
+fn check_type<T: DeserializeOwned>(value: &Value) -> Result<(), serde_json::Error> {
+    T::deserialize(value).map(|_| ())
+}
+
+/// The schemas nested in a described schema, with their JSON paths
+fn child_schemas<'a>(value: &'a Value, path: &str) -> Vec<(String, &'a Value)> {
+    let mut children = Vec::new();
+    if let Some(keys) = value.get("keys").and_then(Value::as_object) {
+        for (key, child) in keys {
+            children.push((format!("{}.keys{}", path, key_segment(key)), child));
+        }
+    }
+    for field in ["items", "ordered", "shared"] {
+        for (index, child) in list(value, field) {
+            children.push((format!("{}.{}[{}]", path, field, index), child));
+        }
+    }
+    for (index, pattern) in list(value, "patterns") {
+        let pattern_path = format!("{}.patterns[{}]", path, index);
+        schema_fields(pattern, &pattern_path, &["schema", "rule"], &mut children);
+    }
+    for field in ["matches", "whens"] {
+        for (index, when) in list(value, field) {
+            let when_path = format!("{}.{}[{}]", path, field, index);
+            let fields = ["schema", "is", "not", "then", "otherwise"];
+            schema_fields(when, &when_path, &fields, &mut children);
+            for (case_index, case) in list(when, "switch") {
+                let case_path = format!("{}.switch[{}]", when_path, case_index);
+                let fields = ["is", "then", "otherwise"];
+                schema_fields(case, &case_path, &fields, &mut children);
+            }
+        }
+    }
+    children
+}
+
+fn list<'a>(value: &'a Value, field: &str) -> impl Iterator<Item = (usize, &'a Value)> {
+    value
+        .get(field)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .enumerate()
+}
+
+fn schema_fields<'a>(
+    value: &'a Value,
+    path: &str,
+    fields: &[&str],
+    children: &mut Vec<(String, &'a Value)>,
+) {
+    for field in fields {
+        if let Some(child) = value.get(field).filter(|child| !child.is_null()) {
+            children.push((format!("{}.{}", path, field), child));
+        }
+    }
+}
+
+/// A step of a JSON path, a key of an object or an index of an array
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// The steps of a JSON path like `$.keys["a b"].items[0]`
+fn path_segments(path: &str) -> Option<Vec<Segment>> {
+    let mut rest = path.strip_prefix('$')?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            segments.push(Segment::Key(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(quoted) = rest.strip_prefix('[').filter(|r| r.starts_with('"')) {
+            // a quoted key is a JSON string
+            let mut strings = serde_json::Deserializer::from_str(quoted).into_iter::<String>();
+            let key = strings.next()?.ok()?;
+            segments.push(Segment::Key(key));
+            rest = quoted[strings.byte_offset()..].strip_prefix(']')?;
+        } else {
+            let after = rest.strip_prefix('[')?;
+            let end = after.find(']')?;
+            segments.push(Segment::Index(after[..end].parse().ok()?));
+            rest = &after[end + 1..];
+        }
+    }
+    Some(segments)
+}
+
+/// Where the value at a JSON path, like `$.keys.name`, is in the JSON source, to point at it in
+/// an error
+pub fn path_span(source: &str, path: &str) -> Option<SourceSpan> {
+    let mut raw: &RawValue = serde_json::from_str(source).ok()?;
+    for segment in path_segments(path)? {
+        raw = match segment {
+            Segment::Key(key) => {
+                let object: BTreeMap<String, &RawValue> = serde_json::from_str(raw.get()).ok()?;
+                object.get(&key).copied()?
+            }
+            Segment::Index(index) => {
+                let array: Vec<&RawValue> = serde_json::from_str(raw.get()).ok()?;
+                array.get(index).copied()?
+            }
+        };
+    }
+    let offset = raw.get().as_ptr() as usize - source.as_ptr() as usize;
+    Some((offset, raw.get().len()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, path_span};
+    use crate::CodeGenError;
+
+    const DESCRIBE: &str = r#"{
+    "type": "object",
+    "keys": {
+        "user": {
+            "type": "array",
+            "items": [{ "type": "object", "keys": ["name"] }]
+        }
+    }
+}"#;
+
+    #[test]
+    fn test_invalid_schema() {
+        let error = parse(DESCRIBE).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid joi `object` schema at $.keys.user.items[0]: invalid type: sequence, expected a map"
+        );
+        let CodeGenError::InvalidSchema { path, .. } = error else {
+            panic!("expected an invalid schema error");
+        };
+        let span = path_span(DESCRIBE, &path).unwrap();
+        assert_eq!(
+            &DESCRIBE[span.offset()..span.offset() + span.len()],
+            r#"{ "type": "object", "keys": ["name"] }"#
+        );
+
+        let describe =
+            r#"{ "type": "object", "keys": { "a b": { "type": "string", "rules": {} } } }"#;
+        let CodeGenError::InvalidSchema { path, .. } = parse(describe).unwrap_err() else {
+            panic!("expected an invalid schema error");
+        };
+        assert_eq!(path, r#"$.keys["a b"]"#);
+        let span = path_span(describe, &path).unwrap();
+        assert_eq!(
+            &describe[span.offset()..span.offset() + span.len()],
+            r#"{ "type": "string", "rules": {} }"#
+        );
+
+        let error = parse(r#"{ "type": "string", "whens": [{ "then": { "flags": {} } }] }"#);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "invalid joi schema at $.whens[0].then: missing field `type`"
+        );
+    }
+}
@@ -0,0 +1,415 @@
+use genco::prelude::js;
+use genco::prelude::*;
+use serde_json::Value;
+
+use crate::joi::{is_override, JoiDescribe, JoiRule};
+use crate::joi_types::{JoiDescribeType, JoiObject, JoiRef};
+use crate::rules::RuleTokens;
+
+// https://joi.dev/api/?v=17.9.1#refkey-options
+
+/// The reference in a rule's arguments, like the limit of `min(Joi.ref("start"))`
+pub fn rule_ref(rule: &JoiRule) -> Option<JoiRef> {
+    let args = rule.args.as_ref()?.as_object()?;
+    args.values().find_map(as_ref)
+}
+
+/// If a value is a described reference
+pub fn is_ref(value: &Value) -> bool {
+    as_ref(value).is_some()
+}
+
+/// Read a described `{ "ref": { ... } }` value
+fn as_ref(value: &Value) -> Option<JoiRef> {
+    serde_json::from_value(value.get("ref")?.clone()).ok()
+}
+
+/// The references in the allow list of a schema, like `valid(Joi.ref("a"))` or `Joi.in("list")`
+pub fn allow_refs(schema: &JoiDescribe) -> Vec<JoiRef> {
+    schema.allow.iter().filter_map(as_ref).collect()
+}
+
+/// The values in an allow list that are not references (or the override marker)
+pub fn allow_literals(allow: &[Value]) -> Vec<Value> {
+    allow
+        .iter()
+        .filter(|value| as_ref(value).is_none() && !is_override(value))
+        .cloned()
+        .collect()
+}
+
+/// The references a schema is checked against, in its rules, its allow list when it only allows
+/// those values, and its invalid values
+pub fn schema_refs(schema: &JoiDescribe) -> Vec<JoiRef> {
+    let rule_refs = schema.rules.iter().filter_map(rule_ref);
+    let only_refs = match schema.flags.only {
+        true => allow_refs(schema),
+        false => Vec::new(),
+    };
+    let invalid_refs = schema.invalid.iter().filter_map(as_ref);
+    rule_refs.chain(only_refs).chain(invalid_refs).collect()
+}
+
+/// If a rule whose argument is a reference can be checked by the object the reference points
+/// into
+pub fn is_checkable(schema: &JoiDescribe, rule: &JoiRule) -> bool {
+    match rule_ref(rule) {
+        Some(reference) => rule_check(schema, rule, &reference, &[]).is_some(),
+        None => false,
+    }
+}
+
+/// A check of a value against a referenced value
+struct RefCheck {
+    /// The keys to the checked value from the object doing the check
+    path: Vec<String>,
+    /// JS function taking the checked value
+    valid: js::Tokens,
+    message: String,
+}
+
+/// JS reading a referenced value from the object doing the check
+fn resolve(reference: &JoiRef) -> js::Tokens {
+    let path = reference.path.iter();
+    quote! {resolve([$(for part in path join (, )=> $[str]($[const](part)))])}
+}
+
+/// The check for a rule whose limit is a reference
+fn rule_check(
+    schema: &JoiDescribe,
+    rule: &JoiRule,
+    reference: &JoiRef,
+    path: &[String],
+) -> Option<RefCheck> {
+    let name = rule.name.as_str();
+    let limit = resolve(reference);
+    // joi shows the referenced key in its messages, like `ref:max`
+    let target = format!("ref:{}", reference.path.join("."));
+    let (size, limit, noun): (js::Tokens, js::Tokens, &str) = match &schema.type_options {
+        JoiDescribeType::Number(_) => (quote! {value}, limit, ""),
+        JoiDescribeType::Date(_) => (
+            quote! {new Date(value).getTime()},
+            quote! {new Date($limit).getTime()},
+            "",
+        ),
+        JoiDescribeType::String(_) => (quote! {value.length}, limit, " characters"),
+        JoiDescribeType::Array(_) => (quote! {value.length}, limit, " items"),
+        JoiDescribeType::Object(_) => (quote! {Object.keys(value).length}, limit, " keys"),
+        _ => return None,
+    };
+    let (check, message) = match (name, noun) {
+        ("multiple", "") => (
+            quote! {value % $limit === 0},
+            format!("Must be a multiple of {}", target),
+        ),
+        ("min", "") => (
+            quote! {$size >= $limit},
+            format!("Must be greater than or equal to {}", target),
+        ),
+        ("max", "") => (
+            quote! {$size <= $limit},
+            format!("Must be less than or equal to {}", target),
+        ),
+        ("greater", "") => (
+            quote! {$size > $limit},
+            format!("Must be greater than {}", target),
+        ),
+        ("less", "") => (
+            quote! {$size < $limit},
+            format!("Must be less than {}", target),
+        ),
+        ("min", noun) => (
+            quote! {$size >= $limit},
+            format!("Must have at least {}{}", target, noun),
+        ),
+        ("max", noun) => (
+            quote! {$size <= $limit},
+            format!("Must have at most {}{}", target, noun),
+        ),
+        ("length", noun) => (
+            quote! {$size === $limit},
+            format!("Must have {}{}", target, noun),
+        ),
+        _ => return None,
+    };
+    Some(RefCheck {
+        path: path.to_vec(),
+        valid: quote! {(value) => $check},
+        message,
+    })
+}
+
+/// The check that a value is one of an allow list containing references
+fn allow_check(schema: &JoiDescribe, references: &[&JoiRef], path: &[String]) -> RefCheck {
+    let literals = allow_literals(&schema.allow);
+    let literals = literals.iter().map(|value| {
+        let value = value.to_string();
+        quote! {$value}
+    });
+    let references = references.iter().map(|reference| {
+        let value = resolve(reference);
+        match reference.in_array {
+            true => quote! {...($value ?? [])},
+            false => value,
+        }
+    });
+    RefCheck {
+        path: path.to_vec(),
+        valid: quote! {(value) => [$(for value in literals.chain(references) join (, )=> $value)].includes(value)},
+        message: "Must be one of the allowed values".to_string(),
+    }
+}
+
+/// Collect the checks of a schema, and the schemas of its keys, whose references point at the
+/// object `level` objects up
+fn collect_checks(schema: &JoiDescribe, path: &[String], level: u64, checks: &mut Vec<RefCheck>) {
+    let at_level = |reference: &JoiRef| reference.ancestor_level() == Some(level);
+
+    let rule_checks = schema.rules.iter().filter_map(|rule| {
+        let reference = rule_ref(rule).filter(at_level)?;
+        rule_check(schema, rule, &reference, path)
+    });
+    checks.extend(rule_checks);
+
+    let only_refs = allow_refs(schema);
+    let only_refs: Vec<&JoiRef> = only_refs.iter().filter(|r| at_level(r)).collect();
+    if schema.flags.only && !only_refs.is_empty() {
+        checks.push(allow_check(schema, &only_refs, path));
+    }
+
+    let invalid_refs: Vec<JoiRef> = schema.invalid.iter().filter_map(as_ref).collect();
+    let invalid_refs = invalid_refs.iter().filter(|r| at_level(r)).map(resolve);
+    let invalid_refs: Vec<js::Tokens> = invalid_refs.collect();
+    if !invalid_refs.is_empty() {
+        checks.push(RefCheck {
+            path: path.to_vec(),
+            valid: quote! {(value) => ![$(for value in invalid_refs join (, )=> $value)].includes(value)},
+            message: "Contains an invalid value".to_string(),
+        });
+    }
+
+    if let JoiDescribeType::Object(object) = &schema.type_options {
+        for (key, child) in object.keys.iter() {
+            let child_path: Vec<String> = path.iter().chain([key]).cloned().collect();
+            collect_checks(child, &child_path, level + 1, checks);
+        }
+    }
+}
+
+/// Check the references of an object's keys, and their keys, that point at the object's values
+pub fn object_refs(object: &JoiObject, out: &mut RuleTokens) {
+    let mut checks = Vec::new();
+    for (key, schema) in object.keys.iter() {
+        collect_checks(schema, std::slice::from_ref(key), 1, &mut checks);
+    }
+    if checks.is_empty() {
+        return;
+    }
+
+    let checks = checks.into_iter().map(|check| {
+        let path = check.path.iter();
+        let valid = check.valid;
+        quote! {
+            check([$(for part in path join (, )=> $[str]($[const](part)))], $valid, $[str]($[const](check.message)));
+        }
+    });
+    out.super_refines.push(quote! {
+        (val, ctx) => {
+            if (val == null) {
+                return;
+            }
+            const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+            const check = (path, valid, message) => {
+                const value = resolve(path);
+                if (value !== undefined && !valid(value)) {
+                    ctx.addIssue({ code: z.ZodIssueCode.custom, message, path });
+                }
+            };
+            $(for check in checks join ($['\r'])=> $check)
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::joi::JoiDescribe;
+    use crate::{gen_with_diagnostics, DiagnosticKind, GenOptions};
+
+    fn convert(describe: &str) -> String {
+        let joi: JoiDescribe = serde_json::from_str(describe).expect("should work...");
+        joi.convert().unwrap()
+    }
+
+    #[test]
+    fn test_sibling_refs() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "keys": {
+                        "start": { "type": "date" },
+                        "end": { "type": "date", "rules": [{ "name": "min", "args": { "date": { "ref": { "path": ["start"] } } } }] },
+                        "options": { "type": "array", "items": [{ "type": "string" }] },
+                        "choice": { "type": "string", "flags": { "only": true }, "allow": ["none", { "ref": { "path": ["options"], "in": true } }] },
+                        "confirm": { "type": "any", "flags": { "only": true }, "allow": [{ "ref": { "path": ["password"] } }] },
+                        "password": { "type": "string" }
+                    }
+                }"#
+            ),
+            r#"z.object({
+    choice: z.string().optional(),
+    confirm: z.any().optional(),
+    end: z.coerce.date().optional(),
+    options: z.array(z.string()).optional(),
+    password: z.string().optional(),
+    start: z.coerce.date().optional()
+}).superRefine((val, ctx) => {
+    if (val == null) {
+        return;
+    }
+    const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+    const check = (path, valid, message) => {
+        const value = resolve(path);
+        if (value !== undefined && !valid(value)) {
+            ctx.addIssue({ code: z.ZodIssueCode.custom, message, path });
+        }
+    };
+    check(["choice"], (value) => ["none", ...(resolve(["options"]) ?? [])].includes(value), "Must be one of the allowed values");
+    check(["confirm"], (value) => [resolve(["password"])].includes(value), "Must be one of the allowed values");
+    check(["end"], (value) => new Date(value).getTime() >= new Date(resolve(["start"])).getTime(), "Must be greater than or equal to ref:start");
+})"#
+        );
+    }
+
+    #[test]
+    fn test_ancestor_refs() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "keys": {
+                        "max": { "type": "number" },
+                        "inner": {
+                            "type": "object",
+                            "keys": {
+                                "count": { "type": "number", "rules": [{ "name": "max", "args": { "limit": { "ref": { "path": ["max"], "ancestor": 2 } } } }] },
+                                "name": { "type": "string", "rules": [{ "name": "max", "args": { "limit": { "ref": { "path": ["count"] } } } }] }
+                            }
+                        }
+                    }
+                }"#
+            ),
+            r#"z.object({
+    inner: z.object({
+        count: z.number().optional(),
+        name: z.string().optional()
+    }).optional().superRefine((val, ctx) => {
+        if (val == null) {
+            return;
+        }
+        const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+        const check = (path, valid, message) => {
+            const value = resolve(path);
+            if (value !== undefined && !valid(value)) {
+                ctx.addIssue({ code: z.ZodIssueCode.custom, message, path });
+            }
+        };
+        check(["name"], (value) => value.length <= resolve(["count"]), "Must have at most ref:count characters");
+    }),
+    max: z.number().optional()
+}).superRefine((val, ctx) => {
+    if (val == null) {
+        return;
+    }
+    const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+    const check = (path, valid, message) => {
+        const value = resolve(path);
+        if (value !== undefined && !valid(value)) {
+            ctx.addIssue({ code: z.ZodIssueCode.custom, message, path });
+        }
+    };
+    check(["inner", "count"], (value) => value <= resolve(["max"]), "Must be less than or equal to ref:max");
+})"#
+        );
+    }
+
+    #[test]
+    fn test_context_ref() {
+        // zod can't see the context joi validates with
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "number",
+                    "rules": [{ "name": "max", "args": { "limit": { "ref": { "path": ["max"], "type": "global" } } } }]
+                }"#
+            ),
+            "z.number().optional()"
+        );
+    }
+
+    #[test]
+    fn test_unchecked_refs() {
+        let describe = r#"{
+            "type": "object",
+            "keys": {
+                "digits": { "type": "number" },
+                "price": { "type": "number", "rules": [{ "name": "precision", "args": { "limit": { "ref": { "path": ["digits"] } } } }] },
+                "max": { "type": "number", "rules": [{ "name": "max", "args": { "limit": { "ref": { "path": ["max"], "type": "global" } } } }] },
+                "list": { "type": "array", "items": [{ "type": "number", "rules": [{ "name": "min", "args": { "limit": { "ref": { "path": ["digits"] } } } }] }] }
+            }
+        }"#;
+        let output = gen_with_diagnostics(describe.to_string(), &GenOptions::default()).unwrap();
+        let found: Vec<(DiagnosticKind, &str, &str)> = output
+            .diagnostics
+            .iter()
+            .map(|d| (d.kind, d.path.as_str(), d.name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    DiagnosticKind::UnresolvedReference,
+                    "$.keys.list.items[0]",
+                    "digits"
+                ),
+                (DiagnosticKind::UnresolvedReference, "$.keys.max", "$max"),
+                (DiagnosticKind::UnsupportedRule, "$.keys.price", "precision"),
+            ]
+        );
+        assert!(!output.code.contains("TODO"));
+    }
+
+    #[test]
+    fn test_multiple_ref() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "keys": {
+                        "a": { "type": "number" },
+                        "b": { "type": "number", "rules": [{ "name": "multiple", "args": { "base": { "ref": { "path": ["a"] } } } }] }
+                    }
+                }"#
+            ),
+            r#"z.object({
+    a: z.number().optional(),
+    b: z.number().optional()
+}).superRefine((val, ctx) => {
+    if (val == null) {
+        return;
+    }
+    const resolve = (path) => path.reduce((obj, key) => obj?.[key], val);
+    const check = (path, valid, message) => {
+        const value = resolve(path);
+        if (value !== undefined && !valid(value)) {
+            ctx.addIssue({ code: z.ZodIssueCode.custom, message, path });
+        }
+    };
+    check(["b"], (value) => value % resolve(["a"]) === 0, "Must be a multiple of ref:a");
+})"#
+        );
+    }
+}
@@ -0,0 +1,2013 @@
+use genco::prelude::js;
+use genco::prelude::*;
+use serde_json::Value;
+
+use crate::diagnostics::{self, Conversion, Diagnostic};
+use crate::joi::{JoiDescribe, JoiRule, Tokenizer};
+use crate::joi_types::{JoiArray, JoiDependency, JoiDescribeType, JoiObject, JoiSymbol};
+use crate::refs;
+use crate::CodeGenError;
+
+// https://github.com/hapijs/joi/blob/7ead57a9f8180895e110f010b425ae411451bd08/lib/types/string.js
+
+/// The zod pieces generated from the rules of a single joi schema
+#[derive(Debug, Default)]
+pub struct RuleTokens {
+    /// Pre process functions to run on the value before parsing, in order
+    /// https://zod.dev/?id=preprocess
+    pub pre_processes: Vec<js::Tokens>,
+    /// Methods that modify the value, like `trim()`. These are chained before any other method
+    /// since joi converts values before validating them
+    pub transforms: Vec<js::Tokens>,
+    /// Methods chained directly onto the base schema, like `email()`
+    pub methods: Vec<js::Tokens>,
+    /// Refine functions applied after the schema (and its flags)
+    /// https://zod.dev/?id=refine
+    pub refines: Vec<js::Tokens>,
+    /// Super refine functions applied after the refines
+    /// https://zod.dev/?id=superrefine
+    pub super_refines: Vec<js::Tokens>,
+    /// What the rules leave out, recorded with the schema
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Build a refine function that lets `undefined`/`null` through since refines are applied after
+/// the presence flags
+pub fn refine(check: js::Tokens, message: &str) -> js::Tokens {
+    quote! {
+        (val) => {
+            return val == null || $check;
+        }, {message: $[str]($[const](message))}
+    }
+}
+
+/// Joi options for a rule are nested under `args.options`
+fn option<'a>(args: Option<&'a Value>, name: &str) -> Option<&'a Value> {
+    args.and_then(|args| args.pointer(&format!("/options/{}", name)))
+}
+
+/// Joi options that can be a single value or a list of values
+fn option_list(args: Option<&Value>, name: &str) -> Vec<String> {
+    match option(args, name) {
+        Some(Value::Array(values)) => values.iter().filter_map(value_as_string).collect(),
+        Some(value) => value_as_string(value).into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+fn value_as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        // regexes are described as `{ "regex": "/.../" }`
+        Value::Object(obj) => obj.get("regex").and_then(value_as_string),
+        _ => None,
+    }
+}
+
+/// The error for a rule missing an argument, with a path relative to the rule
+fn missing_arg(rule: &JoiRule, arg: &str) -> CodeGenError {
+    CodeGenError::MissingRuleArg {
+        rule: rule.name.clone(),
+        path: format!("$.args.{}", arg),
+    }
+}
+
+/// Turn a string rule into zod tokens, returns false if the rule is not a string specific rule
+pub fn string_rule(
+    rule: &JoiRule,
+    schema: &JoiDescribe,
+    out: &mut RuleTokens,
+) -> Result<bool, CodeGenError> {
+    let args = rule.args.as_ref();
+    match rule.name.as_str() {
+        "email" => email(args, out),
+        "uri" => uri(args, out),
+        // `uuid()` is an alias for `guid()` so both describe as guid
+        "guid" => guid(args, out),
+        "ip" => ip(args, out),
+        "hostname" => out.refines.push(refine(
+            quote! {
+                $(HOSTNAME_REGEX).test(val) || z.string().ip().safeParse(val).success
+            },
+            "Invalid hostname",
+        )),
+        // `datetime()` rejects the date only values joi accepts
+        "isoDate" => {
+            out.refines.push(refine(
+                quote! {$(ISO_DATE_REGEX).test(val) && !isNaN(Date.parse(val))},
+                "Invalid ISO 8601 date",
+            ));
+            if schema.preferences.convert() {
+                out.diagnostics.push(diagnostics::partial_rule(
+                    "isoDate",
+                    "Joi converts the date to the ISO 8601 format, add a `transform()` calling `toISOString()`",
+                ));
+            }
+        }
+        // `regex()` is an alias for `pattern()`
+        "pattern" => pattern(args, out).ok_or_else(|| missing_arg(rule, "regex"))?,
+        "isoDuration" => out.refines.push(refine(
+            quote! {$(ISO_DURATION_REGEX).test(val)},
+            "Invalid ISO 8601 duration",
+        )),
+        "trim" | "case" | "normalize" | "truncate" => string_transform(rule, schema, out),
+        "alphanum" => out.methods.push(regex_method(
+            ALPHANUM_REGEX,
+            "Must only contain alpha-numeric characters",
+        )),
+        "token" => out.methods.push(regex_method(
+            TOKEN_REGEX,
+            "Must only contain alpha-numeric and underscore characters",
+        )),
+        "hex" => {
+            let byte_aligned = option(args, "byteAligned")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            if byte_aligned {
+                out.methods.push(regex_method(
+                    HEX_BYTE_ALIGNED_REGEX,
+                    "Must only contain byte aligned hexadecimal characters",
+                ));
+            } else {
+                out.methods.push(regex_method(
+                    HEX_REGEX,
+                    "Must only contain hexadecimal characters",
+                ));
+            }
+        }
+        "base64" => out.methods.push(regex_method(
+            &base64_regex(args),
+            "Must be a valid base64 string",
+        )),
+        "dataUri" => out.methods.push(regex_method(
+            DATA_URI_REGEX,
+            "Must be a valid data uri string",
+        )),
+        "domain" => out.methods.push(regex_method(
+            &domain_regex(args),
+            "Must be a valid domain name",
+        )),
+        // the luhn checksum joi uses
+        "creditCard" => out.refines.push(quote! {
+            (val) => {
+                if (val == null) {
+                    return true;
+                }
+                if (!$(DIGITS_REGEX).test(val)) {
+                    return false;
+                }
+                let sum = 0;
+                let mul = 1;
+                for (let i = val.length - 1; i >= 0; i--) {
+                    const digit = Number(val[i]) * mul;
+                    sum += digit - (digit > 9) * 9;
+                    mul ^= 3;
+                }
+                return sum > 0 && sum % 10 === 0;
+            }, {message: "Must be a credit card number"}
+        }),
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+const HOSTNAME_REGEX: &str = r"/^(?=.{1,255}$)[0-9A-Za-z](?:(?:[0-9A-Za-z]|-){0,61}[0-9A-Za-z])?(?:\.[0-9A-Za-z](?:(?:[0-9A-Za-z]|-){0,61}[0-9A-Za-z])?)*\.?$/";
+
+const ALPHANUM_REGEX: &str = r"/^[a-zA-Z0-9]+$/";
+
+const TOKEN_REGEX: &str = r"/^\w+$/";
+
+const HEX_REGEX: &str = r"/^[a-f0-9]+$/i";
+
+const HEX_BYTE_ALIGNED_REGEX: &str = r"/^(?:[a-f0-9]{2})+$/i";
+
+const DATA_URI_REGEX: &str = r"/^data:[\w+.-]+\/[\w+.-]+;(?:(?:charset=[\w-]+|base64),)?.*$/";
+
+const DIGITS_REGEX: &str = r"/^\d+$/";
+
+const CLASS_REGEX: &str = r"/^\s*class\s/";
+
+const ISO_DATE_REGEX: &str =
+    r"/^\d{4}-\d{2}-\d{2}(?:T\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?)?$/";
+
+const CIDR_PREFIX_REGEX: &str = r"/^\d{1,3}$/";
+
+const ISO_DURATION_REGEX: &str =
+    r"/^P(?!$)(\d+Y)?(\d+M)?(\d+W)?(\d+D)?(T(?=\d)(\d+H)?(\d+M)?(\d+S)?)?$/";
+
+fn email(args: Option<&Value>, out: &mut RuleTokens) {
+    // joi checks the top level domain against the IANA list and accepts unicode unless told not to
+    let tlds = match option(args, "tlds") {
+        Some(Value::Bool(tlds)) => *tlds,
+        Some(tlds) => tlds.get("allow") != Some(&Value::Bool(false)),
+        None => true,
+    };
+    let unicode = option(args, "allowUnicode") != Some(&Value::Bool(false));
+    let segments = option(args, "minDomainSegments")
+        .and_then(Value::as_u64)
+        .is_some_and(|segments| segments != 2);
+    let left_out: Vec<&str> = [
+        (tlds, "`tlds`"),
+        (unicode, "`allowUnicode`"),
+        (segments, "`minDomainSegments`"),
+    ]
+    .into_iter()
+    .filter_map(|(left_out, name)| left_out.then_some(name))
+    .collect();
+    if !left_out.is_empty() {
+        out.diagnostics.push(diagnostics::partial_rule(
+            "email",
+            &format!(
+                "Zod has no equivalent of {}, check it with a `refine()`",
+                left_out.join(", ")
+            ),
+        ));
+    }
+
+    let multiple = option(args, "multiple")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    if !multiple {
+        out.methods.push(quote! {email()});
+        return;
+    }
+
+    let separator = option_list(args, "separator");
+    let separator = separator.first().map(String::as_str).unwrap_or(",");
+    out.refines.push(refine(
+        quote! {
+            val.split($[str]($[const](separator))).every((email) => z.string().email().safeParse(email.trim()).success)
+        },
+        "Invalid email list",
+    ));
+}
+
+fn uri(args: Option<&Value>, out: &mut RuleTokens) {
+    let relative_only = option(args, "relativeOnly") == Some(&Value::Bool(true));
+    let relative = relative_only || option(args, "allowRelative") == Some(&Value::Bool(true));
+    if relative_only {
+        out.diagnostics.push(diagnostics::partial_rule(
+            "uri",
+            "Absolute uris are accepted too, reject them with a `refine()`",
+        ));
+    }
+
+    if relative {
+        // zod's url() only accepts absolute urls so resolve against a dummy base instead
+        out.refines.push(refine(
+            quote! {
+                (() => { try { new URL(val, "http://localhost"); return true; } catch { return false; } })()
+            },
+            "Invalid uri",
+        ));
+    } else {
+        out.methods.push(quote! {url()});
+    }
+
+    let schemes = option_list(args, "scheme");
+    if !schemes.is_empty() {
+        let schemes = schemes
+            .iter()
+            .map(|scheme| {
+                // regex schemes are described with their slashes
+                scheme
+                    .strip_prefix('/')
+                    .and_then(|s| s.rsplit_once('/'))
+                    .map(|(source, _flags)| source.to_string())
+                    .unwrap_or_else(|| escape_regex(scheme))
+            })
+            .collect::<Vec<_>>()
+            .join("|");
+        let scheme_regex = format!("/^(?:{}):/i", schemes);
+        out.refines.push(refine(
+            quote! {$scheme_regex.test(val)},
+            "Invalid uri scheme",
+        ));
+    }
+}
+
+fn guid(args: Option<&Value>, out: &mut RuleTokens) {
+    out.methods.push(quote! {uuid()});
+
+    let versions = option_list(args, "version");
+    // zod's uuid accepts any version so check the version nibble ourselves
+    let nibbles: String = versions
+        .iter()
+        .filter_map(|version| version.strip_prefix("uuidv"))
+        .collect();
+    if !nibbles.is_empty() {
+        let version_regex = format!("/^[0-9a-f]{{8}}-[0-9a-f]{{4}}-[{}]/i", nibbles);
+        out.refines.push(refine(
+            quote! {$version_regex.test(val)},
+            &format!("Invalid uuid, expected {}", versions.join(" or ")),
+        ));
+    }
+}
+
+fn ip(args: Option<&Value>, out: &mut RuleTokens) {
+    let versions = option_list(args, "version");
+    // ipvfuture has no zod equivalent so fall back to accepting any version
+    if versions.iter().any(|version| version == "ipvfuture") {
+        out.diagnostics.push(diagnostics::partial_rule(
+            "ip",
+            "Zod has no equivalent of `ipvfuture`, any ip version is accepted until then",
+        ));
+    }
+    let zod_version = match versions.as_slice() {
+        [version] if version == "ipv4" => Some("v4"),
+        [version] if version == "ipv6" => Some("v6"),
+        _ => None,
+    };
+    let ip_args: js::Tokens = match zod_version {
+        Some(version) => quote! {{ version: $[str]($[const](version)) }},
+        None => quote! {},
+    };
+
+    // joi defaults to allowing an optional cidr, zod has no cidr support so refine instead
+    let cidr = option(args, "cidr")
+        .and_then(Value::as_str)
+        .unwrap_or("optional");
+    match cidr {
+        "forbidden" => out.methods.push(quote! {ip($ip_args)}),
+        "required" => out.refines.push(refine(
+            quote! {
+                val.split("/").length === 2 && z.string().ip($ip_args).safeParse(val.split("/")[0]).success && $(CIDR_PREFIX_REGEX).test(val.split("/")[1])
+            },
+            "Invalid ip address with cidr",
+        )),
+        _ => out.refines.push(refine(
+            quote! {
+                z.string().ip($ip_args).safeParse(val.split("/")[0]).success && (val.split("/").length === 1 || (val.split("/").length === 2 && $(CIDR_PREFIX_REGEX).test(val.split("/")[1])))
+            },
+            "Invalid ip address",
+        )),
+    }
+}
+
+/// Returns `None` when the regex is missing
+fn pattern(args: Option<&Value>, out: &mut RuleTokens) -> Option<()> {
+    let regex = args
+        .and_then(|args| args.get("regex"))
+        .and_then(value_as_string)?;
+    let regex = regex_literal(&regex);
+
+    let name = option(args, "name").and_then(Value::as_str);
+    let invert = option(args, "invert")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    if invert {
+        let message = match name {
+            Some(name) => format!("Must not match the {} pattern", name),
+            None => "Must not match the inverted pattern".to_string(),
+        };
+        out.refines
+            .push(refine(quote! {!$regex.test(val)}, &message));
+    } else {
+        let message = match name {
+            Some(name) => format!("Does not match the {} pattern", name),
+            None => "Does not match the required pattern".to_string(),
+        };
+        out.methods.push(regex_method(&regex, &message));
+    }
+    Some(())
+}
+
+/// A zod `regex()` check with a message
+fn regex_method(regex: &str, message: &str) -> js::Tokens {
+    quote! {regex($regex, { message: $[str]($[const](message)) })}
+}
+
+/// base64 as joi checks it, with the `=` padding being required or optional
+fn base64_regex(args: Option<&Value>) -> String {
+    let padding_required = option(args, "paddingRequired")
+        .and_then(Value::as_bool)
+        .unwrap_or(true);
+    let url_safe = option(args, "urlSafe")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+
+    let chars = if url_safe {
+        r"[\w\-]"
+    } else {
+        r"[A-Za-z0-9+\/]"
+    };
+    let padding = if padding_required {
+        format!("{chars}{{2}}==|{chars}{{3}}=")
+    } else {
+        format!("{chars}{{2}}(?:==)?|{chars}{{3}}=?")
+    };
+    format!("/^(?:{chars}{{4}})*(?:{padding})?$/")
+}
+
+/// A domain name with at least `minDomainSegments` labels
+fn domain_regex(args: Option<&Value>) -> String {
+    let min_segments = option(args, "minDomainSegments")
+        .and_then(Value::as_u64)
+        .unwrap_or(2)
+        .max(1);
+    format!(
+        r"/^(?=.{{1,256}}$)(?:[a-z0-9](?:[a-z0-9-]{{0,61}}[a-z0-9])?\.){{{},}}[a-z0-9](?:[a-z0-9-]{{0,61}}[a-z0-9])?$/i",
+        min_segments - 1
+    )
+}
+
+/// Rules like `trim(false)` are described with `enabled: false` rather than being removed
+fn enabled(rule: &JoiRule) -> bool {
+    rule.args
+        .as_ref()
+        .and_then(|args| args.get("enabled"))
+        .and_then(Value::as_bool)
+        .unwrap_or(true)
+}
+
+/// Turn a number rule into zod tokens, returns false if the rule is not a number specific rule
+pub fn number_rule(
+    rule: &JoiRule,
+    schema: &JoiDescribe,
+    out: &mut RuleTokens,
+) -> Result<bool, CodeGenError> {
+    let args = rule.args.as_ref();
+    let arg = |name: &str| {
+        args.and_then(|args| args.get(name))
+            .filter(|val| !val.is_null())
+            .map(|val| val.to_string())
+            .ok_or_else(|| missing_arg(rule, name))
+    };
+    match rule.name.as_str() {
+        "greater" => {
+            let limit = arg("limit")?;
+            out.methods.push(quote! {gt($limit)});
+        }
+        "less" => {
+            let limit = arg("limit")?;
+            out.methods.push(quote! {lt($limit)});
+        }
+        "multiple" => {
+            let base = arg("base")?;
+            out.methods.push(quote! {multipleOf($base)});
+        }
+        "sign" => match args
+            .and_then(|args| args.get("sign"))
+            .and_then(Value::as_str)
+        {
+            Some("positive") => out.methods.push(quote! {positive()}),
+            Some("negative") => out.methods.push(quote! {negative()}),
+            _ => return Ok(false),
+        },
+        "port" => out.methods.push(quote! {int().min(0).max(65535)}),
+        "precision" => {
+            let Some(limit) = args
+                .and_then(|args| args.get("limit"))
+                .and_then(Value::as_u64)
+            else {
+                return Ok(false);
+            };
+            if schema.preferences.convert() {
+                // joi rounds to the precision when converting rather than failing
+                out.pre_processes.push(quote! {
+                    (val) => {
+                        if (typeof val === "number" && Number.isFinite(val)) {
+                            return Number(val.toFixed($limit));
+                        }
+                        return val;
+                    }
+                });
+            } else {
+                out.refines.push(refine(
+                    quote! {Number(val.toFixed($limit)) === val},
+                    &format!("Must have no more than {} decimal places", limit),
+                ));
+            }
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// How many of the ordered items of an array have to be there, up to the last required one
+pub fn ordered_min_length(array: &JoiArray) -> usize {
+    array
+        .ordered
+        .iter()
+        .rposition(|item| item.flags.presence.as_deref() == Some("required"))
+        .map_or(0, |index| index + 1)
+}
+
+/// The base zod schema for a joi date.
+///
+/// Joi converts strings and numbers into dates unless in strict mode, the `format` flag from
+/// `iso()` or `timestamp()` limits what it will convert from.
+pub fn date_schema(schema: &JoiDescribe, out: &mut RuleTokens) -> js::Tokens {
+    if !schema.preferences.convert() {
+        return quote! { z.date() };
+    }
+
+    match schema.flags.format.as_deref() {
+        Some("iso") => {
+            out.pre_processes.push(quote! {
+                (val) => {
+                    if (typeof val === "string" && $(ISO_DATE_REGEX).test(val)) {
+                        return new Date(val);
+                    }
+                    return val;
+                }
+            });
+            quote! { z.date() }
+        }
+        Some(format @ ("javascript" | "unix")) => {
+            let timestamp: js::Tokens = if format == "unix" {
+                quote! {Number(val) * 1000}
+            } else {
+                quote! {Number(val)}
+            };
+            out.pre_processes.push(quote! {
+                (val) => {
+                    if (typeof val === "number" || (typeof val === "string" && val.trim() !== "" && !isNaN(Number(val)))) {
+                        return new Date($timestamp);
+                    }
+                    return val;
+                }
+            });
+            quote! { z.date() }
+        }
+        _ => quote! { z.coerce.date() },
+    }
+}
+
+/// Limit a date to the values of `valid()`, zod literals can't match dates so they are compared
+/// in a refine
+pub fn valid_dates(schema: &JoiDescribe, out: &mut RuleTokens) {
+    let dates = schema
+        .allow
+        .iter()
+        .filter(|value| value.is_string() || value.is_number());
+    let dates: Vec<String> = dates.map(|value| value.to_string()).collect();
+    out.refines.push(refine(
+        quote! {[$(for date in dates join (, )=> $date)].some((date) => new Date(date).getTime() === val.getTime())},
+        "Must be one of the allowed values",
+    ));
+}
+
+/// Turn a date rule into zod tokens, returns false if the rule is not a date specific rule
+pub fn date_rule(rule: &JoiRule, _schema: &JoiDescribe, out: &mut RuleTokens) -> bool {
+    // min and max are inclusive like zod's, greater and less have no zod equivalent
+    let (method, operator, relation) = match rule.name.as_str() {
+        "min" => (Some("min"), ">=", "on or after"),
+        "max" => (Some("max"), "<=", "on or before"),
+        "greater" => (None, ">", "after"),
+        "less" => (None, "<", "before"),
+        _ => return false,
+    };
+
+    let date = rule.args.as_ref().and_then(|args| args.get("date"));
+    match date {
+        // `now` has to be checked when parsing, not when the schema is made
+        Some(Value::String(date)) if date == "now" => out.refines.push(refine(
+            quote! {val.getTime() $operator Date.now()},
+            &format!("Must be {} now", relation),
+        )),
+        Some(date @ (Value::String(_) | Value::Number(_))) => {
+            let date = date.to_string();
+            match method {
+                Some(method) => out.methods.push(quote! {$method(new Date($(&date)))}),
+                None => out.refines.push(refine(
+                    quote! {val.getTime() $operator new Date($(&date)).getTime()},
+                    &format!("Must be {} {}", relation, date.trim_matches('"')),
+                )),
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Turn an array rule into zod tokens, returns false if the rule is not an array specific rule.
+/// `rule_path` is the JSON path of the rule
+pub fn array_rule(
+    rule: &JoiRule,
+    rule_path: &str,
+    arr: &JoiArray,
+    schema: &JoiDescribe,
+    options: &Conversion,
+    out: &mut RuleTokens,
+) -> Result<bool, CodeGenError> {
+    let args = rule.args.as_ref();
+    match rule.name.as_str() {
+        // tuples have no length methods in zod
+        name @ ("min" | "max" | "length") if !arr.ordered.is_empty() => {
+            let Some(limit) = args
+                .and_then(|args| args.get("limit"))
+                .and_then(Value::as_u64)
+            else {
+                return Ok(false);
+            };
+            let (operator, message) = match name {
+                "min" => (">=", format!("Must contain at least {} items", limit)),
+                "max" => ("<=", format!("Must contain at most {} items", limit)),
+                _ => ("===", format!("Must contain {} items", limit)),
+            };
+            out.refines
+                .push(refine(quote! {val.length $operator $limit}, &message));
+        }
+        "has" => {
+            let Some(mut has) = args
+                .and_then(|args| args.get("schema"))
+                .and_then(|has| serde_json::from_value::<JoiDescribe>(has.clone()).ok())
+            else {
+                return Ok(false);
+            };
+            has.set_paths(&format!("{}.args.schema", rule_path));
+            let has = has.to_tokens(false, options)?;
+            out.refines.push(refine(
+                quote! {val.some((item) => $has.safeParse(item).success)},
+                "Must contain at least one item matching the required schema",
+            ));
+        }
+        "sort" => {
+            let descending = option(args, "order").and_then(Value::as_str) == Some("descending");
+            // `by` is a reference to a key on each item
+            let by: js::Tokens = option(args, "by")
+                .and_then(|by| by.pointer("/ref/path"))
+                .and_then(Value::as_array)
+                .map(|path| {
+                    let path = path.iter().filter_map(|key| match key {
+                        Value::String(key) => Some(key.clone()),
+                        Value::Number(index) => Some(index.to_string()),
+                        _ => None,
+                    });
+                    quote! {$(for key in path => ?.[$[str]($[const](key))])}
+                })
+                .unwrap_or_default();
+            let (lesser, greater) = if descending { ("1", "-1") } else { ("-1", "1") };
+            let compare = quote! {
+                (a, b) => (a$(&by) < b$(&by) ? $lesser : a$(&by) > b$(&by) ? $greater : 0)
+            };
+
+            if schema.preferences.convert() {
+                // joi sorts the array when converting rather than failing
+                out.pre_processes.push(quote! {
+                    (val) => {
+                        if (Array.isArray(val)) {
+                            return [...val].sort($compare);
+                        }
+                        return val;
+                    }
+                });
+            } else {
+                let order = if descending {
+                    "descending"
+                } else {
+                    "ascending"
+                };
+                out.refines.push(refine(
+                    quote! {val.every((item, i) => i === 0 || ($compare)(val[i - 1], item) <= 0)},
+                    &format!("Must be sorted in {} order", order),
+                ));
+            }
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Joi rejects `undefined` array items unless the array is sparse. Items are converted as
+/// required already so this only matters when there is nothing checking the items
+pub fn array_sparse(schema: &JoiDescribe, out: &mut RuleTokens) {
+    if schema.flags.sparse == Some(false) {
+        out.refines.push(refine(
+            quote! {val.every((item) => item !== undefined)},
+            "Must not be a sparse array",
+        ));
+    }
+}
+
+/// A literal, or a union of literals, for a list of allowed values. Values zod has no literal for
+/// (objects and arrays) are skipped
+pub fn literal_schema(values: &[Value]) -> Option<js::Tokens> {
+    let literals: Vec<String> = values
+        .iter()
+        .filter(|value| !value.is_object() && !value.is_array())
+        .map(|value| value.to_string())
+        .collect();
+    match literals.as_slice() {
+        [] => None,
+        [literal] => Some(quote! { z.literal($literal) }),
+        literals => Some(quote! {
+            z.union([$(for literal in literals join (, )=> z.literal($literal))])
+        }),
+    }
+}
+
+/// Add the values of `allow()` to a schema that isn't limited to them. `null` makes the schema
+/// nullable and the other values are added as literals, since joi lets them skip the rules
+pub fn allow_extras(schema: &JoiDescribe, tokens: js::Tokens) -> js::Tokens {
+    match &schema.type_options {
+        // `z.any()` already accepts everything and `nullableString` handles its own allow list
+        _ if schema.flags.only => return tokens,
+        JoiDescribeType::Any(_) | JoiDescribeType::Unknown(_) => return tokens,
+        _ => {}
+    }
+    let extras = allow_extra_literals(schema);
+    let tokens = match extras.is_empty() {
+        true => tokens,
+        false => {
+            quote! { z.union([$tokens, $(for extra in extras join (, )=> z.literal($extra))]) }
+        }
+    };
+    match schema.allow.iter().any(Value::is_null) {
+        true => quote! { $tokens.nullable() },
+        false => tokens,
+    }
+}
+
+/// The values of `allow()` added to a schema as literals, the ones it doesn't accept already
+pub fn allow_extra_literals(schema: &JoiDescribe) -> Vec<String> {
+    match &schema.type_options {
+        _ if schema.flags.only => return Vec::new(),
+        JoiDescribeType::Any(_) | JoiDescribeType::Unknown(_) => return Vec::new(),
+        _ => {}
+    }
+    // without rules to skip the base schema accepts values of its own type anyway
+    let accepted = |value: &Value| {
+        schema.rules.is_empty()
+            && match &schema.type_options {
+                JoiDescribeType::String(_) => value.is_string(),
+                JoiDescribeType::Number(_) => value.is_number(),
+                JoiDescribeType::Boolean(_) => value.is_boolean(),
+                _ => false,
+            }
+    };
+    refs::allow_literals(&schema.allow)
+        .iter()
+        .filter(|value| !value.is_null() && !value.is_object() && !value.is_array())
+        .filter(|value| !accepted(value))
+        .map(|value| value.to_string())
+        .collect()
+}
+
+/// Disallow the values of `invalid()`. References are checked by the object they point into and
+/// booleans are narrowed to the remaining literal instead
+pub fn invalid_values(schema: &JoiDescribe, out: &mut RuleTokens) {
+    let is_boolean = matches!(schema.type_options, JoiDescribeType::Boolean(_));
+    let invalid: Vec<Value> = refs::allow_literals(&schema.invalid)
+        .into_iter()
+        .filter(|value| !(is_boolean && value.is_boolean()))
+        .collect();
+    if invalid.is_empty() {
+        return;
+    }
+
+    let has_null = invalid.iter().any(Value::is_null);
+    let non_null: Vec<String> = invalid
+        .iter()
+        .filter(|value| !value.is_null())
+        .map(|value| value.to_string())
+        .collect();
+    let values = quote! {[$(for value in non_null.iter() join (, )=> $value)]};
+    let check = match &schema.type_options {
+        _ if non_null.is_empty() => quote! {true},
+        JoiDescribeType::Date(_) => {
+            quote! {!$values.some((date) => new Date(date).getTime() === val.getTime())}
+        }
+        _ if invalid
+            .iter()
+            .any(|value| value.is_object() || value.is_array()) =>
+        {
+            quote! {!$values.some((value) => JSON.stringify(value) === JSON.stringify(val))}
+        }
+        _ => quote! {!$values.includes(val)},
+    };
+
+    // the refine helper lets `null` through, which is wrong when `null` is invalid
+    out.refines.push(if has_null {
+        quote! {
+            (val) => {
+                return val === undefined || (val !== null && $check);
+            }, {message: "Contains an invalid value"}
+        }
+    } else {
+        refine(check, "Contains an invalid value")
+    });
+}
+
+/// Turn an object rule into zod tokens, returns false if the rule is not an object specific rule
+pub fn object_rule(rule: &JoiRule, _schema: &JoiDescribe, out: &mut RuleTokens) -> bool {
+    // `instance()` replaces the whole object schema
+    if rule.name == "instance" {
+        return true;
+    }
+    let limit = rule
+        .args
+        .as_ref()
+        .and_then(|args| args.get("limit"))
+        .and_then(Value::as_u64);
+    // min, max and length on objects count the keys
+    let (operator, message) = match (rule.name.as_str(), limit) {
+        ("min", Some(limit)) => (">=", format!("Must have at least {} keys", limit)),
+        ("max", Some(limit)) => ("<=", format!("Must have at most {} keys", limit)),
+        ("length", Some(limit)) => ("===", format!("Must have {} keys", limit)),
+        _ => return false,
+    };
+    let limit = limit.unwrap_or_default();
+    out.refines.push(refine(
+        quote! {Object.keys(val).length $operator $limit},
+        &message,
+    ));
+    true
+}
+
+/// If the schema is `Joi.object().instance(Class)`
+pub fn is_instance(schema: &JoiDescribe) -> bool {
+    schema.rules.iter().any(|rule| rule.name == "instance")
+}
+
+/// The class name of `Joi.object().instance(Class)`. The class itself can't be serialized so the
+/// generated schema expects a class of the same name to be in scope, `None` if the name is not a
+/// usable identifier
+pub fn instance_of(schema: &JoiDescribe) -> Option<String> {
+    let rule = schema.rules.iter().find(|rule| rule.name == "instance")?;
+    let name = rule.args.as_ref().and_then(|args| args.get("name"));
+    let name = name.and_then(Value::as_str).filter(|name| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    });
+    name.map(str::to_string)
+}
+
+/// A binary schema, strings are converted to buffers with the schema's encoding like joi does
+pub fn binary_schema(schema: &JoiDescribe, out: &mut RuleTokens) -> js::Tokens {
+    if schema.preferences.convert() {
+        let from = match &schema.flags.encoding {
+            Some(encoding) => quote! {Buffer.from(val, $[str]($[const](encoding)))},
+            None => quote! {Buffer.from(val)},
+        };
+        out.pre_processes.push(quote! {
+            (val) => {
+                if (typeof val === "string") {
+                    return $from;
+                }
+                return val;
+            }
+        });
+    }
+    quote! { z.instanceof(Buffer) }
+}
+
+/// Turn a binary rule into zod tokens, returns false if the rule is not a binary specific rule
+pub fn binary_rule(rule: &JoiRule, _schema: &JoiDescribe, out: &mut RuleTokens) -> bool {
+    let limit = rule
+        .args
+        .as_ref()
+        .and_then(|args| args.get("limit"))
+        .and_then(Value::as_u64);
+    let (operator, message) = match (rule.name.as_str(), limit) {
+        ("min", Some(limit)) => (">=", format!("Must be at least {} bytes", limit)),
+        ("max", Some(limit)) => ("<=", format!("Must be at most {} bytes", limit)),
+        ("length", Some(limit)) => ("===", format!("Must be {} bytes", limit)),
+        _ => return false,
+    };
+    let limit = limit.unwrap_or_default();
+    out.refines
+        .push(refine(quote! {val.length $operator $limit}, &message));
+    true
+}
+
+/// A symbol schema, the values of the symbol map are accepted too when converting since joi turns
+/// them into their symbols. The symbols aren't in the describe output, so the map is reported by
+/// `Conversion::record`
+pub fn symbol_schema(symbol: &JoiSymbol, schema: &JoiDescribe) -> js::Tokens {
+    let values = symbol_map_literals(symbol, schema);
+    if values.is_empty() {
+        return quote! { z.symbol() };
+    }
+    quote! { z.union([z.symbol(), $(for value in values join (, )=> z.literal($value))]) }
+}
+
+/// The values of the symbol map a symbol schema accepts as literals
+pub fn symbol_map_literals(symbol: &JoiSymbol, schema: &JoiDescribe) -> Vec<String> {
+    if !schema.preferences.convert() {
+        return Vec::new();
+    }
+    symbol
+        .map
+        .iter()
+        .filter(|(value, _)| !value.is_object() && !value.is_array())
+        .map(|(value, _)| value.to_string())
+        .collect()
+}
+
+/// A function schema. `z.function()` wraps the function it parses so the arity rules need the
+/// original function, checked with `z.custom()` instead
+pub fn function_schema(schema: &JoiDescribe) -> js::Tokens {
+    let arity_rules = ["arity", "minArity", "maxArity", "class"];
+    if schema
+        .rules
+        .iter()
+        .any(|rule| arity_rules.contains(&rule.name.as_str()))
+    {
+        quote! { z.custom((val) => typeof val === "function", { message: "Must be a function" }) }
+    } else {
+        quote! { z.function() }
+    }
+}
+
+/// Turn a function rule into zod tokens, returns false if the rule is not a function specific rule
+pub fn function_rule(rule: &JoiRule, _schema: &JoiDescribe, out: &mut RuleTokens) -> bool {
+    let n = rule
+        .args
+        .as_ref()
+        .and_then(|args| args.get("n"))
+        .and_then(Value::as_u64);
+    let (operator, message) = match (rule.name.as_str(), n) {
+        ("arity", Some(n)) => ("===", format!("Must have an arity of {}", n)),
+        ("minArity", Some(n)) => (">=", format!("Must have an arity of at least {}", n)),
+        ("maxArity", Some(n)) => ("<=", format!("Must have an arity of at most {}", n)),
+        ("class", _) => {
+            out.refines.push(refine(
+                quote! {$(CLASS_REGEX).test(Function.prototype.toString.call(val))},
+                "Must be a class",
+            ));
+            return true;
+        }
+        _ => return false,
+    };
+    let n = n.unwrap_or_default();
+    out.refines
+        .push(refine(quote! {val.length $operator $n}, &message));
+    true
+}
+
+/// If a dependency can be checked, an unknown relation or a `with`/`without` without its main key
+/// can't be and is reported by `Conversion::record`
+pub fn is_supported_dependency(dep: &JoiDependency) -> bool {
+    match (dep.rel.as_str(), &dep.key) {
+        ("and" | "or" | "xor" | "oxor", _) => true,
+        ("nand", _) => !dep.peers.is_empty(),
+        ("with" | "without", Some(_)) => true,
+        _ => false,
+    }
+}
+
+/// Check the `and`, `or`, `xor`, `nand`, `oxor`, `with` and `without` dependencies of an object
+/// in a single super refine.
+///
+/// Joi reports dependency errors on the object itself rather than the keys involved so the issues
+/// have no extra path either.
+pub fn object_dependencies(object: &JoiObject, out: &mut RuleTokens) {
+    let dependencies = object
+        .dependencies
+        .iter()
+        .filter(|dep| is_supported_dependency(dep));
+    let dependencies: Vec<&JoiDependency> = dependencies.collect();
+    if dependencies.is_empty() {
+        return;
+    }
+
+    let checks = dependencies.into_iter().filter_map(|dep| {
+        let separator = dep.options.separator.as_deref().unwrap_or(".");
+        let path = |key: &str| -> js::Tokens {
+            let parts = key.split(separator);
+            quote! {[$(for part in parts join (, )=> $[str]($[const](part)))]}
+        };
+        let list = |keys: &[String]| {
+            keys.iter()
+                .map(|key| format!("\"{}\"", key))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let peers = dep.peers.iter().map(|peer| path(peer)).collect::<Vec<_>>();
+        let peers: js::Tokens = quote! {[$(for peer in peers join (, )=> $peer)]};
+        let issue = |message: String| -> js::Tokens {
+            quote! {
+                ctx.addIssue({ code: z.ZodIssueCode.custom, message: $[str]($[const](message)) });
+            }
+        };
+
+        let check = match (dep.rel.as_str(), dep.key.as_deref()) {
+            ("and", _) => {
+                let message = format!("Must contain all or none of [{}]", list(&dep.peers));
+                quote! {
+                    if ($(&peers).some(present) && !$(&peers).every(present)) {
+                        $(issue(message))
+                    }
+                }
+            }
+            ("or", _) => {
+                let message = format!("Must contain at least one of [{}]", list(&dep.peers));
+                quote! {
+                    if (!$(&peers).some(present)) {
+                        $(issue(message))
+                    }
+                }
+            }
+            ("xor", _) => {
+                let missing = format!("Must contain at least one of [{}]", list(&dep.peers));
+                let conflict = format!(
+                    "Contains a conflict between exclusive peers [{}]",
+                    list(&dep.peers)
+                );
+                quote! {
+                    if ($(&peers).filter(present).length === 0) {
+                        $(issue(missing))
+                    } else if ($(&peers).filter(present).length > 1) {
+                        $(issue(conflict))
+                    }
+                }
+            }
+            ("oxor", _) => {
+                let message = format!(
+                    "Contains a conflict between optional exclusive peers [{}]",
+                    list(&dep.peers)
+                );
+                quote! {
+                    if ($(&peers).filter(present).length > 1) {
+                        $(issue(message))
+                    }
+                }
+            }
+            ("nand", _) => {
+                let (main, rest) = dep.peers.split_first()?;
+                let message = format!(
+                    "\"{}\" must not exist simultaneously with [{}]",
+                    main,
+                    list(rest)
+                );
+                quote! {
+                    if ($(&peers).every(present)) {
+                        $(issue(message))
+                    }
+                }
+            }
+            ("with", Some(key)) => {
+                let checks = dep.peers.iter().map(|peer| {
+                    let message = format!("\"{}\" missing required peer \"{}\"", key, peer);
+                    quote! {
+                        if (!present($(path(peer)))) {
+                            $(issue(message))
+                        }
+                    }
+                });
+                quote! {
+                    if (present($(path(key)))) {
+                        $(for check in checks join ($['\r']) => $check)
+                    }
+                }
+            }
+            ("without", Some(key)) => {
+                let checks = dep.peers.iter().map(|peer| {
+                    let message = format!("\"{}\" conflict with forbidden peer \"{}\"", key, peer);
+                    quote! {
+                        if (present($(path(peer)))) {
+                            $(issue(message))
+                        }
+                    }
+                });
+                quote! {
+                    if (present($(path(key)))) {
+                        $(for check in checks join ($['\r']) => $check)
+                    }
+                }
+            }
+            _ => return None,
+        };
+        Some(check)
+    });
+
+    out.super_refines.push(quote! {
+        (val, ctx) => {
+            if (val == null) {
+                return;
+            }
+            const present = (path) => path.reduce((obj, key) => obj?.[key], val) !== undefined;
+            $(for check in checks join ($['\r']) => $check)
+        }
+    });
+}
+
+/// `catchall()` only checks the values of unlisted keys so refine that the keys match a pattern
+/// too, unless joi allows unknown keys anyway
+pub fn object_pattern_keys(
+    object: &JoiObject,
+    patterns: &[(js::Tokens, js::Tokens)],
+    schema: &JoiDescribe,
+    out: &mut RuleTokens,
+) {
+    if schema.flags.unknown == Some(true) {
+        return;
+    }
+    let known = object.keys.keys();
+    out.refines.push(refine(
+        quote! {
+            Object.keys(val).every((key) => [$(for key in known join (, )=> $[str]($[const](key)))].includes(key) || $(for (key_schema, _) in patterns join ( || )=> $key_schema.safeParse(key).success))
+        },
+        "Must only have keys matching the object patterns",
+    ));
+}
+
+/// Joi string rules that convert the value, or only check it is already converted in strict mode
+fn string_transform(rule: &JoiRule, schema: &JoiDescribe, out: &mut RuleTokens) {
+    let args = rule.args.as_ref();
+    if !enabled(rule) {
+        return;
+    }
+    let convert = schema.preferences.convert();
+
+    match rule.name.as_str() {
+        "trim" if convert => out.transforms.push(quote! {trim()}),
+        "trim" => out.refines.push(refine(
+            quote! {val === val.trim()},
+            "Must not have leading or trailing whitespace",
+        )),
+        "case" => {
+            let (method, message) = match args
+                .and_then(|args| args.get("direction"))
+                .and_then(Value::as_str)
+            {
+                Some("upper") => ("toUpperCase", "Must only contain upper case characters"),
+                _ => ("toLowerCase", "Must only contain lower case characters"),
+            };
+            if convert {
+                out.transforms.push(quote! {$method()});
+            } else {
+                out.refines
+                    .push(refine(quote! {val === val.$method()}, message));
+            }
+        }
+        "normalize" => {
+            let form = args
+                .and_then(|args| args.get("form"))
+                .and_then(Value::as_str)
+                .unwrap_or("NFC");
+            // zod has no normalize method, but joi normalizes before anything else so a pre
+            // process matches it
+            if convert {
+                out.pre_processes.push(quote! {
+                    (val) => {
+                        if (typeof val === "string") {
+                            return val.normalize($[str]($[const](form)));
+                        }
+                        return val;
+                    }
+                });
+            } else {
+                out.refines.push(refine(
+                    quote! {val === val.normalize($[str]($[const](form)))},
+                    &format!("Must be unicode normalized in the {} form", form),
+                ));
+            }
+        }
+        // truncate only does anything with a max rule, otherwise the max check just fails
+        "truncate" if convert => {
+            let Some(limit) = schema
+                .rules
+                .iter()
+                .find(|rule| rule.name == "max")
+                .and_then(|rule| rule.args.as_ref())
+                .and_then(|args| args.get("limit"))
+                .and_then(Value::as_u64)
+            else {
+                return;
+            };
+            // joi trims before truncating
+            let trim = schema
+                .rules
+                .iter()
+                .any(|rule| rule.name == "trim" && enabled(rule));
+            let value: js::Tokens = if trim {
+                quote! {val.trim()}
+            } else {
+                quote! {val}
+            };
+            out.pre_processes.push(quote! {
+                (val) => {
+                    if (typeof val === "string") {
+                        return $value.slice(0, $limit);
+                    }
+                    return val;
+                }
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Rebuild a js regex literal from the `/source/flags` string joi describes a regex as.
+///
+/// Unescaped `/` in the source are escaped so the literal can't end early and the stateful `g`
+/// and `y` flags (which joi rejects anyway) are dropped so `.test()` stays repeatable.
+pub fn regex_literal(described: &str) -> String {
+    let (source, flags) = described
+        .strip_prefix('/')
+        .and_then(|rest| rest.rsplit_once('/'))
+        .unwrap_or((described, ""));
+
+    let mut escaped = String::with_capacity(source.len() + 2);
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                escaped.push(c);
+                if let Some(next) = chars.next() {
+                    escaped.push(next);
+                }
+            }
+            '/' => escaped.push_str("\\/"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    if escaped.is_empty() {
+        // `//` would be a comment
+        escaped.push_str("(?:)");
+    }
+
+    let flags: String = flags.chars().filter(|f| "dimsu".contains(*f)).collect();
+    format!("/{}/{}", escaped, flags)
+}
+
+/// Escape a literal string so it can be embedded in a js regex
+pub fn escape_regex(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if "\\^$.|?*+()[]{}/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::regex_literal;
+    use crate::joi::JoiDescribe;
+
+    fn convert(describe: &str) -> String {
+        let joi: JoiDescribe = serde_json::from_str(describe).expect("should work...");
+        joi.convert().unwrap()
+    }
+
+    #[test]
+    fn test_string_email() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "email", "args": { "options": {} } }]
+                }"#
+            ),
+            "z.string().email()"
+        );
+    }
+
+    #[test]
+    fn test_string_multiple_email() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "email", "args": { "options": { "multiple": true, "separator": ";" } } }]
+                }"#
+            ),
+            r#"z.string().refine((val) => {
+    return val == null || val.split(";").every((email) => z.string().email().safeParse(email.trim()).success);
+}, {message: "Invalid email list"})"#
+        );
+    }
+
+    #[test]
+    fn test_string_uri_with_scheme() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "uri", "args": { "options": { "scheme": ["http", "git+ssh"] } } }]
+                }"#
+            ),
+            r#"z.string().url().refine((val) => {
+    return val == null || /^(?:http|git\+ssh):/i.test(val);
+}, {message: "Invalid uri scheme"})"#
+        );
+    }
+
+    #[test]
+    fn test_string_guid_version() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "rules": [{ "name": "guid", "args": { "options": { "version": ["uuidv4", "uuidv5"] } } }]
+                }"#
+            ),
+            r#"z.string().uuid().optional().refine((val) => {
+    return val == null || /^[0-9a-f]{8}-[0-9a-f]{4}-[45]/i.test(val);
+}, {message: "Invalid uuid, expected uuidv4 or uuidv5"})"#
+        );
+    }
+
+    #[test]
+    fn test_string_ip() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "ip", "args": { "options": { "version": ["ipv4"], "cidr": "forbidden" } } }]
+                }"#
+            ),
+            r#"z.string().ip({ version: "v4" })"#
+        );
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "ip", "args": { "options": { "cidr": "required" } } }]
+                }"#
+            ),
+            r#"z.string().refine((val) => {
+    return val == null || val.split("/").length === 2 && z.string().ip().safeParse(val.split("/")[0]).success && /^\d{1,3}$/.test(val.split("/")[1]);
+}, {message: "Invalid ip address with cidr"})"#
+        );
+    }
+
+    #[test]
+    fn test_string_pattern() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "pattern", "args": { "regex": "/^[a-z]+$/i", "options": {} } }]
+                }"#
+            ),
+            r#"z.string().regex(/^[a-z]+$/i, { message: "Does not match the required pattern" })"#
+        );
+    }
+
+    #[test]
+    fn test_string_pattern_inverted() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "pattern", "args": { "regex": "/^admin$/", "options": { "name": "reserved", "invert": true } } }]
+                }"#
+            ),
+            r#"z.string().refine((val) => {
+    return val == null || !/^admin$/.test(val);
+}, {message: "Must not match the reserved pattern"})"#
+        );
+    }
+
+    #[test]
+    fn test_regex_literal() {
+        // already escaped slashes are kept as is
+        assert_eq!(regex_literal(r"/^a\/b$/"), r"/^a\/b$/");
+        // unescaped slashes and new lines would break the literal
+        assert_eq!(regex_literal("/^a/b\n$/m"), r"/^a\/b\n$/m");
+        assert_eq!(regex_literal("^[/]+$"), r"/^[\/]+$/");
+        // escaped backslash followed by a slash
+        assert_eq!(regex_literal(r"/a\\/b/"), r"/a\\\/b/");
+        // stateful flags are dropped
+        assert_eq!(regex_literal("/abc/gimy"), "/abc/im");
+        assert_eq!(regex_literal("//"), "/(?:)/");
+    }
+
+    #[test]
+    fn test_string_transforms() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [
+                        { "name": "min", "args": { "limit": 2 } },
+                        { "name": "trim", "args": { "enabled": true } },
+                        { "name": "case", "args": { "direction": "upper" } }
+                    ]
+                }"#
+            ),
+            "z.string().trim().toUpperCase().min(2)"
+        );
+    }
+
+    #[test]
+    fn test_string_transforms_strict() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "preferences": { "convert": false },
+                    "rules": [
+                        { "name": "trim", "args": { "enabled": true } },
+                        { "name": "case", "args": { "direction": "lower" } }
+                    ]
+                }"#
+            ),
+            r#"z.string().refine((val) => {
+    return val == null || val === val.trim();
+}, {message: "Must not have leading or trailing whitespace"}).refine((val) => {
+    return val == null || val === val.toLowerCase();
+}, {message: "Must only contain lower case characters"})"#
+        );
+    }
+
+    #[test]
+    fn test_string_normalize_and_truncate() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [
+                        { "name": "normalize", "args": { "form": "NFD" } },
+                        { "name": "max", "args": { "limit": 10 } },
+                        { "name": "truncate", "args": { "enabled": true } },
+                        { "name": "trim", "args": { "enabled": false } }
+                    ]
+                }"#
+            ),
+            r#"z.preprocess((val) => {
+    if (typeof val === "string") {
+        return val.normalize("NFD");
+    }
+    return val;
+}, z.preprocess((val) => {
+    if (typeof val === "string") {
+        return val.slice(0, 10);
+    }
+    return val;
+}, z.string().max(10)))"#
+        );
+    }
+
+    #[test]
+    fn test_string_character_rules() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [
+                        { "name": "length", "args": { "limit": 24 } },
+                        { "name": "hex", "args": { "options": { "byteAligned": true } } },
+                        { "name": "token" }
+                    ]
+                }"#
+            ),
+            r#"z.string().length(24).regex(/^(?:[a-f0-9]{2})+$/i, { message: "Must only contain byte aligned hexadecimal characters" }).regex(/^\w+$/, { message: "Must only contain alpha-numeric and underscore characters" })"#
+        );
+    }
+
+    #[test]
+    fn test_string_base64() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "base64", "args": { "options": { "paddingRequired": false, "urlSafe": true } } }]
+                }"#
+            ),
+            r#"z.string().regex(/^(?:[\w\-]{4})*(?:[\w\-]{2}(?:==)?|[\w\-]{3}=?)?$/, { message: "Must be a valid base64 string" })"#
+        );
+    }
+
+    #[test]
+    fn test_string_domain() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "domain", "args": { "options": { "minDomainSegments": 3 } } }]
+                }"#
+            ),
+            r#"z.string().regex(/^(?=.{1,256}$)(?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.){2,}[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?$/i, { message: "Must be a valid domain name" })"#
+        );
+    }
+
+    #[test]
+    fn test_string_credit_card() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "creditCard" }]
+                }"#
+            ),
+            r#"z.string().refine((val) => {
+    if (val == null) {
+        return true;
+    }
+    if (!/^\d+$/.test(val)) {
+        return false;
+    }
+    let sum = 0;
+    let mul = 1;
+    for (let i = val.length - 1; i >= 0; i--) {
+        const digit = Number(val[i]) * mul;
+        sum += digit - (digit > 9) * 9;
+        mul ^= 3;
+    }
+    return sum > 0 && sum % 10 === 0;
+}, {message: "Must be a credit card number"})"#
+        );
+    }
+
+    #[test]
+    fn test_number_rules() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "number",
+                    "flags": { "presence": "required" },
+                    "rules": [
+                        { "name": "greater", "args": { "limit": 0 } },
+                        { "name": "less", "args": { "limit": 100.5 } },
+                        { "name": "sign", "args": { "sign": "positive" } },
+                        { "name": "multiple", "args": { "base": 0.01 } }
+                    ]
+                }"#
+            ),
+            "z.number().gt(0).lt(100.5).positive().multipleOf(0.01)"
+        );
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "number",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "port" }]
+                }"#
+            ),
+            "z.number().int().min(0).max(65535)"
+        );
+    }
+
+    #[test]
+    fn test_number_precision() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "number",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "precision", "args": { "limit": 2 } }]
+                }"#
+            ),
+            r#"z.preprocess((val) => {
+    if (typeof val === "number" && Number.isFinite(val)) {
+        return Number(val.toFixed(2));
+    }
+    return val;
+}, z.number())"#
+        );
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "number",
+                    "flags": { "presence": "required" },
+                    "preferences": { "convert": false },
+                    "rules": [{ "name": "precision", "args": { "limit": 2 } }]
+                }"#
+            ),
+            r#"z.number().refine((val) => {
+    return val == null || Number(val.toFixed(2)) === val;
+}, {message: "Must have no more than 2 decimal places"})"#
+        );
+    }
+
+    #[test]
+    fn test_date_coercion() {
+        assert_eq!(
+            convert(r#"{ "type": "date", "flags": { "presence": "required" } }"#),
+            "z.coerce.date()"
+        );
+        assert_eq!(
+            convert(
+                r#"{ "type": "date", "flags": { "presence": "required" }, "preferences": { "convert": false } }"#
+            ),
+            "z.date()"
+        );
+        assert_eq!(
+            convert(r#"{ "type": "date", "flags": { "presence": "required", "format": "unix" } }"#),
+            r#"z.preprocess((val) => {
+    if (typeof val === "number" || (typeof val === "string" && val.trim() !== "" && !isNaN(Number(val)))) {
+        return new Date(Number(val) * 1000);
+    }
+    return val;
+}, z.date())"#
+        );
+    }
+
+    #[test]
+    fn test_date_rules() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "date",
+                    "flags": { "presence": "required", "format": "iso" },
+                    "rules": [
+                        { "name": "min", "args": { "date": "2020-01-01T00:00:00.000Z" } },
+                        { "name": "less", "args": { "date": "now" } }
+                    ]
+                }"#
+            ),
+            r#"z.preprocess((val) => {
+    if (typeof val === "string" && /^\d{4}-\d{2}-\d{2}(?:T\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?)?$/.test(val)) {
+        return new Date(val);
+    }
+    return val;
+}, z.date().min(new Date("2020-01-01T00:00:00.000Z")).refine((val) => {
+    return val == null || val.getTime() < Date.now();
+}, {message: "Must be before now"}))"#
+        );
+    }
+
+    #[test]
+    fn test_array_ordered() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "array",
+                    "flags": { "presence": "required" },
+                    "ordered": [{ "type": "string", "flags": { "presence": "required" } }, { "type": "number", "flags": { "presence": "required" } }],
+                    "items": [{ "type": "boolean" }],
+                    "rules": [{ "name": "max", "args": { "limit": 4 } }]
+                }"#
+            ),
+            r#"z.tuple([z.string(), z.number()]).rest(z.boolean()).refine((val) => {
+    return val == null || val.length <= 4;
+}, {message: "Must contain at most 4 items"})"#
+        );
+        // joi accepts `["a"]` since only the first item is required
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "array",
+                    "flags": { "presence": "required" },
+                    "ordered": [{ "type": "string", "flags": { "presence": "required" } }, { "type": "number" }, { "type": "boolean" }]
+                }"#
+            ),
+            "z.union([z.tuple([z.string()]), z.tuple([z.string(), z.number().optional()]), z.tuple([z.string(), z.number().optional(), z.boolean().optional()])])"
+        );
+    }
+
+    #[test]
+    fn test_array_length_rules() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "array",
+                    "flags": { "presence": "required", "sparse": true },
+                    "items": [{ "type": "string" }],
+                    "rules": [
+                        { "name": "min", "args": { "limit": 1 } },
+                        { "name": "length", "args": { "limit": 3 } }
+                    ]
+                }"#
+            ),
+            "z.array(z.string().optional()).min(1).length(3)"
+        );
+    }
+
+    #[test]
+    fn test_array_has() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "array",
+                    "flags": { "presence": "required" },
+                    "items": [{ "type": "number" }],
+                    "rules": [{ "name": "has", "args": { "schema": { "type": "number", "rules": [{ "name": "greater", "args": { "limit": 10 } }] } } }]
+                }"#
+            ),
+            r#"z.array(z.number()).refine((val) => {
+    return val == null || val.some((item) => z.number().gt(10).safeParse(item).success);
+}, {message: "Must contain at least one item matching the required schema"})"#
+        );
+    }
+
+    #[test]
+    fn test_array_sort() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "array",
+                    "flags": { "presence": "required" },
+                    "items": [{ "type": "object" }],
+                    "rules": [{ "name": "sort", "args": { "options": { "order": "descending", "by": { "ref": { "path": ["a", "b"] } } } } }]
+                }"#
+            ),
+            r#"z.preprocess((val) => {
+    if (Array.isArray(val)) {
+        return [...val].sort((a, b) => (a?.["a"]?.["b"] < b?.["a"]?.["b"] ? 1 : a?.["a"]?.["b"] > b?.["a"]?.["b"] ? -1 : 0));
+    }
+    return val;
+}, z.array(z.object({})))"#
+        );
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "array",
+                    "flags": { "presence": "required", "sparse": false },
+                    "preferences": { "convert": false },
+                    "items": [{ "type": "number" }],
+                    "rules": [{ "name": "sort", "args": { "options": { "order": "ascending" } } }]
+                }"#
+            ),
+            r#"z.array(z.number()).refine((val) => {
+    return val == null || val.every((item, i) => i === 0 || ((a, b) => (a < b ? -1 : a > b ? 1 : 0))(val[i - 1], item) <= 0);
+}, {message: "Must be sorted in ascending order"}).refine((val) => {
+    return val == null || val.every((item) => item !== undefined);
+}, {message: "Must not be a sparse array"})"#
+        );
+    }
+
+    #[test]
+    fn test_object_unknown() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required", "unknown": true },
+                    "keys": { "a": { "type": "string" } }
+                }"#
+            ),
+            "z.object({\n    a: z.string().optional()\n}).passthrough()"
+        );
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required", "unknown": false },
+                    "keys": { "a": { "type": "string" } }
+                }"#
+            ),
+            "z.object({\n    a: z.string().optional()\n}).strict()"
+        );
+    }
+
+    #[test]
+    fn test_object_pattern_record() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "patterns": [{ "regex": "/^[a-z]+$/", "rule": { "type": "number" } }],
+                    "rules": [{ "name": "max", "args": { "limit": 10 } }]
+                }"#
+            ),
+            r#"z.record(z.string().regex(/^[a-z]+$/), z.number()).refine((val) => {
+    return val == null || Object.keys(val).length <= 10;
+}, {message: "Must have at most 10 keys"})"#
+        );
+    }
+
+    #[test]
+    fn test_object_pattern_with_keys() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "keys": { "id": { "type": "string", "flags": { "presence": "required" } } },
+                    "patterns": [{ "schema": { "type": "string", "rules": [{ "name": "min", "args": { "limit": 2 } }] }, "rule": { "type": "boolean" } }]
+                }"#
+            ),
+            r#"z.object({
+    id: z.string()
+}).catchall(z.boolean()).refine((val) => {
+    return val == null || Object.keys(val).every((key) => ["id"].includes(key) || z.string().min(2).safeParse(key).success);
+}, {message: "Must only have keys matching the object patterns"})"#
+        );
+    }
+
+    #[test]
+    fn test_object_dependencies() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "keys": {
+                        "a": { "type": "string" },
+                        "b": { "type": "string" },
+                        "c": { "type": "object", "keys": { "d": { "type": "number" } } }
+                    },
+                    "dependencies": [
+                        { "rel": "and", "key": null, "peers": ["a", "b"] },
+                        { "rel": "xor", "peers": ["a", "c.d"] },
+                        { "rel": "with", "key": "a", "peers": ["b", "c/d"], "options": { "separator": "/" } }
+                    ]
+                }"#
+            ),
+            r#"z.object({
+    a: z.string().optional(),
+    b: z.string().optional(),
+    c: z.object({
+        d: z.number().optional()
+    }).optional()
+}).superRefine((val, ctx) => {
+    if (val == null) {
+        return;
+    }
+    const present = (path) => path.reduce((obj, key) => obj?.[key], val) !== undefined;
+    if ([["a"], ["b"]].some(present) && ![["a"], ["b"]].every(present)) {
+        ctx.addIssue({ code: z.ZodIssueCode.custom, message: "Must contain all or none of [\"a\", \"b\"]" });
+    }
+    if ([["a"], ["c", "d"]].filter(present).length === 0) {
+        ctx.addIssue({ code: z.ZodIssueCode.custom, message: "Must contain at least one of [\"a\", \"c.d\"]" });
+    } else if ([["a"], ["c", "d"]].filter(present).length > 1) {
+        ctx.addIssue({ code: z.ZodIssueCode.custom, message: "Contains a conflict between exclusive peers [\"a\", \"c.d\"]" });
+    }
+    if (present(["a"])) {
+        if (!present(["b"])) {
+            ctx.addIssue({ code: z.ZodIssueCode.custom, message: "\"a\" missing required peer \"b\"" });
+        }
+        if (!present(["c", "d"])) {
+            ctx.addIssue({ code: z.ZodIssueCode.custom, message: "\"a\" missing required peer \"c/d\"" });
+        }
+    }
+})"#
+        );
+        // nothing is left to check, the dependencies are reported as diagnostics
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "object",
+                    "flags": { "presence": "required" },
+                    "dependencies": [
+                        { "rel": "with", "key": null, "peers": ["a"] },
+                        { "rel": "someday", "peers": ["a", "b"] }
+                    ]
+                }"#
+            ),
+            "z.object({})"
+        );
+    }
+
+    #[test]
+    fn test_string_iso_date() {
+        assert_eq!(
+            convert(
+                r#"{
+                    "type": "string",
+                    "flags": { "presence": "required" },
+                    "rules": [{ "name": "isoDate" }]
+                }"#
+            ),
+            r#"z.string().refine((val) => {
+    return val == null || /^\d{4}-\d{2}-\d{2}(?:T\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?)?$/.test(val) && !isNaN(Date.parse(val));
+}, {message: "Invalid ISO 8601 date"})"#
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert_eq!(
+            convert(r#"{ "type": "number", "invalid": [0, null] }"#),
+            r#"z.number().optional().refine((val) => {
+    return val === undefined || (val !== null && ![0].includes(val));
+}, {message: "Contains an invalid value"})"#
+        );
+        assert_eq!(
+            convert(r#"{ "type": "date", "invalid": ["2000-01-01T00:00:00.000Z"] }"#),
+            r#"z.coerce.date().optional().refine((val) => {
+    return val == null || !["2000-01-01T00:00:00.000Z"].some((date) => new Date(date).getTime() === val.getTime());
+}, {message: "Contains an invalid value"})"#
+        );
+        assert_eq!(
+            convert(
+                r#"{ "type": "boolean", "flags": { "presence": "required" }, "invalid": [false] }"#
+            ),
+            "z.literal(true)"
+        );
+    }
+
+    #[test]
+    fn test_allow_values() {
+        assert_eq!(
+            convert(r#"{ "type": "number", "allow": [null] }"#),
+            "z.number().nullable().optional()"
+        );
+        assert_eq!(
+            convert(
+                r#"{ "type": "object", "flags": { "presence": "required" }, "allow": [null], "keys": {} }"#
+            ),
+            "z.object({}).nullable()"
+        );
+        assert_eq!(
+            convert(r#"{ "type": "boolean", "allow": ["yes"] }"#),
+            r#"z.union([z.boolean(), z.literal("yes")]).optional()"#
+        );
+        assert_eq!(
+            convert(
+                r#"{ "type": "string", "allow": [""], "rules": [{ "name": "min", "args": { "limit": 3 } }] }"#
+            ),
+            r#"z.union([z.string().min(3), z.literal("")]).optional()"#
+        );
+        // the allowed values skip the refines as well
+        assert_eq!(
+            convert(
+                r#"{ "type": "string", "allow": ["", "none"], "invalid": ["x"], "rules": [{ "name": "isoDuration" }] }"#
+            ),
+            r#"z.union([z.string().refine((val) => {
+    return val == null || /^P(?!$)(\d+Y)?(\d+M)?(\d+W)?(\d+D)?(T(?=\d)(\d+H)?(\d+M)?(\d+S)?)?$/.test(val);
+}, {message: "Invalid ISO 8601 duration"}).refine((val) => {
+    return val == null || !["x"].includes(val);
+}, {message: "Contains an invalid value"}), z.literal(""), z.literal("none")]).optional()"#
+        );
+    }
+
+    #[test]
+    fn test_valid_values() {
+        assert_eq!(
+            convert(r#"{ "type": "any", "flags": { "only": true }, "allow": [1, "a", true] }"#),
+            r#"z.union([z.literal(1), z.literal("a"), z.literal(true)]).optional()"#
+        );
+        assert_eq!(
+            convert(
+                r#"{ "type": "boolean", "flags": { "only": true, "presence": "required" }, "allow": [true] }"#
+            ),
+            "z.literal(true)"
+        );
+        assert_eq!(
+            convert(
+                r#"{ "type": "date", "flags": { "only": true }, "allow": ["2000-01-01T00:00:00.000Z"] }"#
+            ),
+            r#"z.coerce.date().optional().refine((val) => {
+    return val == null || ["2000-01-01T00:00:00.000Z"].some((date) => new Date(date).getTime() === val.getTime());
+}, {message: "Must be one of the allowed values"})"#
+        );
+    }
+
+    #[test]
+    fn test_binary() {
+        assert_eq!(
+            convert(
+                r#"{ "type": "binary", "flags": { "encoding": "base64" }, "rules": [{ "name": "min", "args": { "limit": 2 } }] }"#
+            ),
+            r#"z.preprocess((val) => {
+    if (typeof val === "string") {
+        return Buffer.from(val, "base64");
+    }
+    return val;
+}, z.instanceof(Buffer).optional().refine((val) => {
+    return val == null || val.length >= 2;
+}, {message: "Must be at least 2 bytes"}))"#
+        );
+    }
+
+    #[test]
+    fn test_symbol() {
+        assert_eq!(
+            convert(r#"{ "type": "symbol", "map": [[1, null], ["two", null]] }"#),
+            r#"z.union([z.symbol(), z.literal(1), z.literal("two")]).optional()"#
+        );
+    }
+
+    #[test]
+    fn test_function() {
+        assert_eq!(
+            convert(r#"{ "type": "function" }"#),
+            "z.function().optional()"
+        );
+        assert_eq!(
+            convert(
+                r#"{ "type": "function", "rules": [{ "name": "minArity", "args": { "n": 1 } }] }"#
+            ),
+            r#"z.custom((val) => typeof val === "function", { message: "Must be a function" }).optional().refine((val) => {
+    return val == null || val.length >= 1;
+}, {message: "Must have an arity of at least 1"})"#
+        );
+    }
+
+    #[test]
+    fn test_object_instance() {
+        assert_eq!(
+            convert(
+                r#"{ "type": "object", "rules": [{ "name": "instance", "args": { "name": "RegExp" } }] }"#
+            ),
+            "z.instanceof(RegExp).optional()"
+        );
+        // an anonymous class has no name to check against
+        assert_eq!(
+            convert(
+                r#"{ "type": "object", "rules": [{ "name": "instance", "args": { "name": "" } }] }"#
+            ),
+            "z.any().optional()"
+        );
+    }
+}
@@ -1,8 +1,54 @@
 import test from "ava";
 
-import { toZod } from "../index.js";
+import { toZod, toZodWithWarnings } from "../index.js";
 import Joi from "joi";
 
 test("run code gen", (t) => {
-  t.deepEqual(toZod(Joi.number()), "z.number()");
+  t.deepEqual(toZod(Joi.number()), "z.number().optional()");
+});
+
+test("run code gen with extensions", (t) => {
+  t.deepEqual(
+    toZod({ type: "money" }, { extensions: { types: { money: "z.number()" } } }),
+    "z.number().optional()"
+  );
+});
+
+test("run code gen with warnings", (t) => {
+  const { code, warnings } = toZodWithWarnings(Joi.object({ name: Joi.string().strip() }));
+  t.deepEqual(code, toZod(Joi.object({ name: Joi.string().strip() })));
+  t.deepEqual(
+    warnings.map((warning) => [warning.code, warning.path]),
+    [["joi_to_zod::unsupported_flag", "$.keys.name"]]
+  );
+});
+
+test("run code gen in strict mode", (t) => {
+  t.throws(() => toZod(Joi.object({ name: Joi.string().strip() }), { strict: true }), {
+    code: "joi_to_zod::lossy",
+    message: /unsupported joi flag `strip` at \$\.keys\.name/,
+  });
+});
+
+test("run code gen with a malformed schema", (t) => {
+  t.throws(() => toZod({ type: "string", rules: [{ name: "min", args: {} }] }), {
+    code: "joi_to_zod::missing_rule_arg",
+    message: "missing argument of rule `min` at $.rules[0].args.limit",
+  });
+});
+
+test("run code gen as a module", (t) => {
+  const user = Joi.object({ name: Joi.string().required() }).meta({ className: "User" });
+  t.deepEqual(
+    toZod([user, Joi.number()], { module: { exportTypes: true } }),
+    `import { z } from "zod";
+
+export const UserSchema = z.object({
+    name: z.string()
+}).optional();
+export type User = z.infer<typeof UserSchema>;
+export const Unnamed2Schema = z.number().optional();
+export type Unnamed2 = z.infer<typeof Unnamed2Schema>;
+`
+  );
 });
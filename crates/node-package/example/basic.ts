@@ -1,4 +1,4 @@
-import { toZod } from "../index.js";
+import { toZod, toZodWithWarnings } from "../index.js";
 import Joi from "joi";
 import { ObjetWithWhen } from "./schemas";
 
@@ -61,4 +61,6 @@ console.log(
     4
   )}~~~~~~~~~~~~~~~~~~~`
 );
-console.log(toZod(TestObjWithStrip));
+const { code, warnings } = toZodWithWarnings(TestObjWithStrip);
+console.log(code);
+warnings.forEach((warning) => console.warn(warning.message));
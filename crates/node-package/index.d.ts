@@ -3,4 +3,52 @@
 
 /* auto-generated by NAPI-RS */
 
-export function toZod(joiSchema: object): string
+/** Templates for custom joi types and rules */
+export interface ExtensionOptions {
+  /** zod code per custom type name, like `{ money: "z.number().multipleOf({flags.precision})" }` */
+  types?: Record<string, string>
+  /** zod method per custom rule name, like `{ slug: "regex(/^[a-z-]+$/)" }` */
+  rules?: Record<string, string>
+}
+/** How a complete module is generated, instead of a bare zod expression */
+export interface ModuleOutputOptions {
+  /** Where `z` is imported from, `"zod"` by default */
+  zodImport?: string
+  /** Generate TypeScript, `true` by default */
+  typescript?: boolean
+  /** Export the inferred type of each schema, like `export type User = z.infer<typeof UserSchema>` */
+  exportTypes?: boolean
+}
+export interface ToZodOptions {
+  /** Use `z.unknown()` instead of `z.any()` for arrays without items */
+  unknownArrayItems?: boolean
+  extensions?: ExtensionOptions
+  /** Put a `// TODO` comment before each schema with parts that can't be converted */
+  todoComments?: boolean
+  /** Throw instead of leaving out parts of the joi schema that can't be converted */
+  strict?: boolean
+  /**
+   * Generate a module exporting the schema, or each schema of a list of them, as
+   * `<Name>Schema` named by the `className` meta
+   */
+  module?: ModuleOutputOptions
+}
+/** A part of the joi schema left out of the zod schema */
+export interface ToZodWarning {
+  /** Like `joi_to_zod::unsupported_rule` */
+  code: string
+  message: string
+  /** JSON path to the schema in the describe output, like `$.keys.name` */
+  path: string
+  /** The name of the joi type, rule or flag */
+  name: string
+  suggestion: string
+}
+/** The zod schema, and what of the joi schema it leaves out */
+export interface ToZodOutput {
+  code: string
+  warnings: Array<ToZodWarning>
+}
+export function toZod(joiSchema: object, options?: ToZodOptions | undefined | null): string
+/** Like `toZod`, with the parts of the joi schema the zod schema leaves out as warnings */
+export function toZodWithWarnings(joiSchema: object, options?: ToZodOptions | undefined | null): ToZodOutput
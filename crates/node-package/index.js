@@ -252,6 +252,7 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { toZod } = nativeBinding
+const { toZod, toZodWithWarnings } = nativeBinding
 
 module.exports.toZod = toZod
+module.exports.toZodWithWarnings = toZodWithWarnings
@@ -1,19 +1,163 @@
+use std::collections::HashMap;
+
 use napi::{bindgen_prelude::*, JSON};
 use napi_derive::napi;
 
-use code_gen::gen;
+use code_gen::{
+  gen_with_diagnostics, CodeGenError, Diagnostic, ExtensionConfig, Extensions, GenOptions,
+  GenOutput, ModuleOptions,
+};
 
-#[napi]
-pub fn to_zod(env: Env, joi_schema: Object) -> napi::Result<String> {
-  let describe_obj = match joi_schema.get::<&str, JsFunction>("describe") {
+/// Templates for custom joi types and rules
+#[napi(object)]
+pub struct ExtensionOptions {
+  /// zod code per custom type name, like `{ money: "z.number().multipleOf({flags.precision})" }`
+  pub types: Option<HashMap<String, String>>,
+  /// zod method per custom rule name, like `{ slug: "regex(/^[a-z-]+$/)" }`
+  pub rules: Option<HashMap<String, String>>,
+}
+
+/// How a complete module is generated, instead of a bare zod expression
+#[napi(object)]
+pub struct ModuleOutputOptions {
+  /// Where `z` is imported from, `"zod"` by default
+  pub zod_import: Option<String>,
+  /// Generate TypeScript, `true` by default
+  pub typescript: Option<bool>,
+  /// Export the inferred type of each schema, like `export type User = z.infer<typeof UserSchema>`
+  pub export_types: Option<bool>,
+}
+
+impl From<ModuleOutputOptions> for ModuleOptions {
+  fn from(options: ModuleOutputOptions) -> Self {
+    let defaults = ModuleOptions::default();
+    ModuleOptions {
+      zod_import: options.zod_import.unwrap_or(defaults.zod_import),
+      typescript: options.typescript.unwrap_or(defaults.typescript),
+      export_types: options.export_types.unwrap_or(defaults.export_types),
+    }
+  }
+}
+
+#[napi(object)]
+pub struct ToZodOptions {
+  /// Use `z.unknown()` instead of `z.any()` for arrays without items
+  pub unknown_array_items: Option<bool>,
+  pub extensions: Option<ExtensionOptions>,
+  /// Put a `// TODO` comment before each schema with parts that can't be converted
+  pub todo_comments: Option<bool>,
+  /// Throw instead of leaving out parts of the joi schema that can't be converted
+  pub strict: Option<bool>,
+  /// Generate a module exporting the schema, or each schema of a list of them, as
+  /// `<Name>Schema` named by the `className` meta
+  pub module: Option<ModuleOutputOptions>,
+}
+
+/// A part of the joi schema left out of the zod schema
+#[napi(object)]
+pub struct ToZodWarning {
+  /// Like `joi_to_zod::unsupported_rule`
+  pub code: String,
+  pub message: String,
+  /// JSON path to the schema in the describe output, like `$.keys.name`
+  pub path: String,
+  /// The name of the joi type, rule or flag
+  pub name: String,
+  pub suggestion: String,
+}
+
+impl From<Diagnostic> for ToZodWarning {
+  fn from(diagnostic: Diagnostic) -> Self {
+    ToZodWarning {
+      code: diagnostic.kind.code().to_string(),
+      message: diagnostic.to_string(),
+      path: diagnostic.path,
+      name: diagnostic.name,
+      suggestion: diagnostic.suggestion,
+    }
+  }
+}
+
+/// The zod schema, and what of the joi schema it leaves out
+#[napi(object)]
+pub struct ToZodOutput {
+  pub code: String,
+  pub warnings: Vec<ToZodWarning>,
+}
+
+impl From<ToZodOptions> for GenOptions {
+  fn from(options: ToZodOptions) -> Self {
+    let mut extensions = Extensions::default();
+    if let Some(config) = options.extensions {
+      extensions.register_config(ExtensionConfig {
+        types: config.types.unwrap_or_default().into_iter().collect(),
+        rules: config.rules.unwrap_or_default().into_iter().collect(),
+      });
+    }
+    GenOptions {
+      unknown_array_items: options.unknown_array_items.unwrap_or_default(),
+      extensions,
+      todo_comments: options.todo_comments.unwrap_or_default(),
+      strict: options.strict.unwrap_or_default(),
+      module: options.module.map(ModuleOptions::from),
+    }
+  }
+}
+
+/// Throw the error in JS with its code as the `code` property, like
+/// `joi_to_zod::missing_rule_arg`
+fn throw_gen_error(env: Env, error: CodeGenError) -> napi::Error {
+  match env.throw_error(&error.to_string(), Some(error.code())) {
+    Ok(()) => napi::Error::from_status(napi::Status::PendingException),
+    Err(error) => error,
+  }
+}
+
+/// The describe output of a joi schema, or of each joi schema of a list of them
+fn describe(env: Env, joi_schema: Object) -> napi::Result<Object> {
+  if joi_schema.is_array()? {
+    let length = joi_schema.get_array_length()?;
+    let mut described = env.create_array_with_length(length as usize)?;
+    for index in 0..length {
+      let schema = describe(env, joi_schema.get_element::<Object>(index)?)?;
+      described.set_element(index, schema)?;
+    }
+    return Ok(described);
+  }
+  match joi_schema.get::<&str, JsFunction>("describe") {
     Ok(Some(func)) => func
       .call_without_args(Some(&joi_schema))?
-      .coerce_to_object()?,
-    _ => joi_schema,
-  };
+      .coerce_to_object(),
+    _ => Ok(joi_schema),
+  }
+}
+
+fn convert(env: Env, joi_schema: Object, options: Option<ToZodOptions>) -> napi::Result<GenOutput> {
+  let describe_obj = describe(env, joi_schema)?;
   let json: JSON = env.get_global()?.get_named_property_unchecked("JSON")?;
-  Ok(
-    gen(json.stringify(describe_obj)?)
-      .map_err(|e| napi::Error::new(napi::Status::GenericFailure, format!("{}", e)))?,
-  )
+  let options = options.map(GenOptions::from).unwrap_or_default();
+  gen_with_diagnostics(json.stringify(describe_obj)?, &options).map_err(|e| throw_gen_error(env, e))
+}
+
+#[napi]
+pub fn to_zod(env: Env, joi_schema: Object, options: Option<ToZodOptions>) -> napi::Result<String> {
+  Ok(convert(env, joi_schema, options)?.code)
+}
+
+/// Like `toZod`, with the parts of the joi schema the zod schema leaves out as warnings
+#[napi]
+pub fn to_zod_with_warnings(
+  env: Env,
+  joi_schema: Object,
+  options: Option<ToZodOptions>,
+) -> napi::Result<ToZodOutput> {
+  let output = convert(env, joi_schema, options)?;
+  Ok(ToZodOutput {
+    code: output.code,
+    warnings: output
+      .diagnostics
+      .into_iter()
+      .map(ToZodWarning::from)
+      .collect(),
+  })
 }